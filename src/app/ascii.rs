@@ -14,6 +14,18 @@ pub fn the_end() -> String {
     .to_string()
 }
 
+pub fn you_win() -> String {
+    indoc! {"
+        ██    ██  ██████  ██    ██    ██     ██ ██ ███    ██
+         ██  ██  ██    ██ ██    ██    ██     ██ ██ ████   ██
+          ████   ██    ██ ██    ██    ██  █  ██ ██ ██ ██  ██
+           ██    ██    ██ ██    ██    ██ ███ ██ ██ ██  ██ ██
+           ██    ██    ██ ██    ██     ███ ███  ██ ██   ████
+           ██     ██████   ██████       █   █   ██ ██    ███
+    "}
+    .to_string()
+}
+
 pub fn god_fall() -> String {
     indoc! {"
            ████      ████    ██████        ███████    ███    ██      ██     
@@ -1,3 +1,40 @@
+//! 通过 JNI 调用 Java 端 `PlayerService` 的数据层
+//!
+//! Java 端的实体类和服务需要与这里的调用一致。约定改变时递增 [`CONTRACT_VERSION`]，
+//! Java 端在 `GlobalVariables.CONTRACT_VERSION` 中写明自己实现的版本，启动时两边不一致
+//! 会直接报错，而不是等到某次调用时才因为找不到方法失败。
+//!
+//! 版本 2 相对最初的版本：
+//!
+//! - `entity.PlayerRecord` 增加字段 `int boardSize`、`long seed`、`int undoCount`、
+//!   `boolean won`、`int maxTile`、`int moves`、`int merges`、`int movesUp`、
+//!   `int movesDown`、`int movesLeft`、`int movesRight`、`int mode`。构造函数依次接收
+//!   玩家编号、`score`、`time`、`timestamp` 和以上字段，签名为 `(IIJJIJIZIIIIIIII)V`
+//! - `PlayerService` 增加 `String loadGame(Player)` 和 `boolean saveGame(Player, String)`，
+//!   `saveGame` 的存档为 null 时删除存档
+//!
+//! 版本 3：
+//!
+//! - `entity.PlayerRecord` 增加字段 `long puzzle`，构造函数在末尾接收，签名变为
+//!   `(IIJJIJIZIIIIIIIIJ)V`
+//!
+//! 版本 4：
+//!
+//! - `PlayerService.updatePlayer` 只修改玩家名，不再用传入的记录替换已有的记录
+//! - `PlayerService` 增加 `boolean removeRecord(Player)`，删除该玩家与 `records` 中
+//!   时间戳、分数和种子都相同的记录，玩家不存在时返回 false
+//!
+//! 版本 5：
+//!
+//! - `PlayerService` 的失败一律抛出 `service.exception` 包中的异常，由前端逐一对应到
+//!   [`DataError`]：`AuthFailedException` 为密码错误，`PlayerNotFoundException` 为玩家
+//!   不存在，`ConflictException` 为与已有数据冲突（例如重名），`ValidationException`
+//!   为数据不合法，后两者的消息会显示给玩家。其他异常都视为后端出错
+//! - `getCurrentPlayer` 在没有登录或玩家已被删除时抛出 `PlayerNotFoundException`
+//! - `getCurrentPlayer`、`verifyAccount`、`registerAccount` 不再以 null 表示失败，
+//!   `saveRecord`、`updatePlayer`、`removePlayer`、`removeRecord`、`saveGame` 不再以
+//!   false 表示失败。返回 null 或 false 属于违反约定，前端按后端出错处理
+
 #![allow(dead_code)]
 
 use std::{
@@ -7,12 +44,18 @@ use std::{
 
 use jni::{
     JNIEnv,
-    objects::{JObject, JValueGen},
+    objects::{JObject, JString, JValueGen},
 };
 
 use crate::app::structs::{Player, PlayerRecord};
 
-use super::{DataManager, TryRecvError};
+use super::{DataError, DataManager};
+
+/// Java 端需要实现的约定版本
+pub const CONTRACT_VERSION: i32 = 5;
+
+/// Java 端报告失败原因的异常类所在的包
+const EXCEPTION_PACKAGE: &str = "com/smoother/TacticalGrid2048/service/exception";
 
 #[derive(PartialEq, Eq)]
 pub enum RequestBody {
@@ -25,6 +68,9 @@ pub enum RequestBody {
     FindPlayer(Player),
     UpdatePlayer(Player),
     RemovePlayer(Player),
+    RemoveRecord(Player),
+    LoadGame(Player),
+    SaveGame(Player, Option<String>),
     Exit,
 }
 
@@ -40,22 +86,28 @@ impl RequestBody {
             Self::FindPlayer { .. } => 6,
             Self::UpdatePlayer { .. } => 7,
             Self::RemovePlayer { .. } => 8,
-            Self::Exit => 9,
+            Self::LoadGame { .. } => 9,
+            Self::SaveGame { .. } => 10,
+            Self::Exit => 11,
+            Self::RemoveRecord { .. } => 12,
         }
     }
 }
 
 #[derive(PartialEq, Eq)]
 pub enum ResponseBody {
-    GetCurrentPlayer(Player),
-    GetPlayersBestExceptSelf(Vec<Player>),
-    GetPlayers(Vec<Player>),
-    SaveRecord(bool),
-    VerifyAccount(Option<Player>),
-    RegisterAccount(Option<Player>),
-    FindPlayer(Vec<Player>),
-    UpdatePlayer(bool),
-    RemovePlayer(bool),
+    GetCurrentPlayer(Result<Player, DataError>),
+    GetPlayersBestExceptSelf(Result<Vec<Player>, DataError>),
+    GetPlayers(Result<Vec<Player>, DataError>),
+    SaveRecord(Result<(), DataError>),
+    VerifyAccount(Result<Player, DataError>),
+    RegisterAccount(Result<Player, DataError>),
+    FindPlayer(Result<Vec<Player>, DataError>),
+    UpdatePlayer(Result<(), DataError>),
+    RemovePlayer(Result<(), DataError>),
+    RemoveRecord(Result<(), DataError>),
+    LoadGame(Result<Option<String>, DataError>),
+    SaveGame(Result<(), DataError>),
 }
 
 pub type Request = (RequestBody, usize);
@@ -137,10 +189,10 @@ macro_rules! impl_request_response {
         if !has_pending {
             let seq = $this.next_seq();
             if $this.tx.send(($request, seq)).is_err() {
-                return Err(TryRecvError::Disconnected);
+                return Err(DataError::Disconnected);
             } else {
                 $this.requests.push(((request_ty, seq), Instant::now()));
-                return Err(TryRecvError::Empty);
+                return Err(DataError::Empty);
             }
         }
 
@@ -157,15 +209,15 @@ macro_rules! impl_request_response {
                 let ResponseBody::$rsp_variant(result) = resp else {
                     unreachable!()
                 };
-                return Ok(result);
+                return result;
             }
         }
 
         if $this.check_expired(request_ty) {
-            return Err(TryRecvError::Timeout);
+            return Err(DataError::Timeout);
         }
 
-        return Err(TryRecvError::Empty);
+        return Err(DataError::Empty);
     }
 }
 
@@ -174,27 +226,23 @@ impl DataManager for JniDataManager {
         self.is_first_launch
     }
 
-    fn get_current_player(&mut self) -> Result<Player, TryRecvError> {
+    fn get_current_player(&mut self) -> Result<Player, DataError> {
         impl_request_response!(self, GetCurrentPlayer, GetCurrentPlayer);
     }
 
-    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, TryRecvError> {
+    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, DataError> {
         impl_request_response!(self, GetPlayersBestExceptSelf, GetPlayersBestExceptSelf);
     }
 
-    fn get_players(&mut self) -> Result<Vec<Player>, TryRecvError> {
+    fn get_players(&mut self) -> Result<Vec<Player>, DataError> {
         impl_request_response!(self, GetPlayers, GetPlayers);
     }
 
-    fn save_record(&mut self, player: Player) -> Result<bool, TryRecvError> {
+    fn save_record(&mut self, player: Player) -> Result<(), DataError> {
         impl_request_response!(self, SaveRecord(player), SaveRecord);
     }
 
-    fn verify_account(
-        &mut self,
-        username: String,
-        password: String,
-    ) -> Result<Option<Player>, TryRecvError> {
+    fn verify_account(&mut self, username: String, password: String) -> Result<Player, DataError> {
         impl_request_response!(self, VerifyAccount(username, password), VerifyAccount);
     }
 
@@ -202,21 +250,114 @@ impl DataManager for JniDataManager {
         &mut self,
         username: String,
         password: String,
-    ) -> Result<Option<Player>, TryRecvError> {
+    ) -> Result<Player, DataError> {
         impl_request_response!(self, RegisterAccount(username, password), RegisterAccount);
     }
 
-    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, TryRecvError> {
+    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, DataError> {
         impl_request_response!(self, FindPlayer(player), FindPlayer);
     }
 
-    fn update_player(&mut self, player: Player) -> Result<bool, TryRecvError> {
+    fn update_player(&mut self, player: Player) -> Result<(), DataError> {
         impl_request_response!(self, UpdatePlayer(player), UpdatePlayer);
     }
 
-    fn remove_player(&mut self, player: Player) -> Result<bool, TryRecvError> {
+    fn remove_player(&mut self, player: Player) -> Result<(), DataError> {
         impl_request_response!(self, RemovePlayer(player), RemovePlayer);
     }
+
+    fn remove_record(&mut self, player: Player) -> Result<(), DataError> {
+        impl_request_response!(self, RemoveRecord(player), RemoveRecord);
+    }
+
+    fn load_game(&mut self, player: Player) -> Result<Option<String>, DataError> {
+        impl_request_response!(self, LoadGame(player), LoadGame);
+    }
+
+    fn save_game(&mut self, player: Player, game: Option<String>) -> Result<(), DataError> {
+        impl_request_response!(self, SaveGame(player, game), SaveGame);
+    }
+}
+
+/// 把 JNI 调用的结果转换成数据层的结果，Java 抛出的异常会被清除，按异常的类型转换成对应的错误
+pub fn java_result<T>(
+    env: &mut JNIEnv<'_>,
+    result: jni::errors::Result<T>,
+) -> Result<T, DataError> {
+    let e = match result {
+        Ok(x) => return Ok(x),
+        Err(e) => e,
+    };
+    if !matches!(e, jni::errors::Error::JavaException) {
+        return Err(DataError::Backend(e.to_string()));
+    }
+    let Ok(exception) = env.exception_occurred() else {
+        return Err(DataError::Backend(e.to_string()));
+    };
+    // 异常不清除的话，之后的每次调用都会失败
+    let _ = env.exception_clear();
+    let is_a = |env: &mut JNIEnv<'_>, name: &str| {
+        let class = format!("{EXCEPTION_PACKAGE}/{name}");
+        let result = env.is_instance_of(&exception, class.as_str());
+        // Java 端缺少这个类时查找本身也会抛出异常
+        let _ = env.exception_clear();
+        result.unwrap_or(false)
+    };
+    if is_a(env, "AuthFailedException") {
+        return Err(DataError::AuthFailed);
+    }
+    if is_a(env, "PlayerNotFoundException") {
+        return Err(DataError::NotFound);
+    }
+    if is_a(env, "ConflictException") {
+        let message = java_string(env, &exception, "getMessage");
+        return Err(DataError::Conflict(
+            message.unwrap_or_else(|| String::from("与已有的数据冲突")),
+        ));
+    }
+    if is_a(env, "ValidationException") {
+        let message = java_string(env, &exception, "getMessage");
+        return Err(DataError::Validation(
+            message.unwrap_or_else(|| String::from("提交的数据不合法")),
+        ));
+    }
+    let message = java_string(env, &exception, "toString");
+    Err(DataError::Backend(message.unwrap_or_else(|| e.to_string())))
+}
+
+/// 调用返回字符串的无参方法，失败或返回 null 时为 `None`
+fn java_string(env: &mut JNIEnv<'_>, o: &JObject<'_>, method: &str) -> Option<String> {
+    let result = env
+        .call_method(o, method, "()Ljava/lang/String;", &[])
+        .and_then(|x| x.l())
+        .and_then(|x| {
+            if x.is_null() {
+                return Ok(None);
+            }
+            Ok(Some(env.get_string(&JString::from(x))?.into()))
+        });
+    let _ = env.exception_clear();
+    result.ok().flatten()
+}
+
+/// 失败应以异常报告，返回 false 说明 Java 端违反了约定
+pub fn require(ok: bool, method: &str) -> Result<(), DataError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(contract_violation(method, "false"))
+    }
+}
+
+/// 失败应以异常报告，返回 null 说明 Java 端违反了约定
+pub fn require_some<T>(x: Option<T>, method: &str) -> Result<T, DataError> {
+    x.ok_or_else(|| contract_violation(method, "null"))
+}
+
+fn contract_violation(method: &str, value: &str) -> DataError {
+    DataError::Backend(format!(
+        "PlayerService.{method} 返回了 {value}，不符合数据接口版本 {CONTRACT_VERSION} 的约定"
+    ))
 }
 
 fn parse_java_list<'local, T, F>(
@@ -273,10 +414,36 @@ fn get_player_from_java<'local>(
             let score = env.get_field(o, "score", "I")?.i()?;
             let time = env.get_field(o, "time", "J")?.j()?;
             let timestamp = env.get_field(o, "timestamp", "J")?.j()?;
+            let board_size = env.get_field(o, "boardSize", "I")?.i()?;
+            let seed = env.get_field(o, "seed", "J")?.j()?;
+            let undo_count = env.get_field(o, "undoCount", "I")?.i()?;
+            let won = env.get_field(o, "won", "Z")?.z()?;
+            let max_tile = env.get_field(o, "maxTile", "I")?.i()?;
+            let moves = env.get_field(o, "moves", "I")?.i()?;
+            let merges = env.get_field(o, "merges", "I")?.i()?;
+            let moves_up = env.get_field(o, "movesUp", "I")?.i()?;
+            let moves_down = env.get_field(o, "movesDown", "I")?.i()?;
+            let moves_left = env.get_field(o, "movesLeft", "I")?.i()?;
+            let moves_right = env.get_field(o, "movesRight", "I")?.i()?;
+            let mode = env.get_field(o, "mode", "I")?.i()?;
+            let puzzle = env.get_field(o, "puzzle", "J")?.j()?;
             Ok(PlayerRecord {
                 score,
                 time,
                 timestamp,
+                board_size,
+                seed,
+                undo_count,
+                won,
+                max_tile,
+                moves,
+                merges,
+                moves_up,
+                moves_down,
+                moves_left,
+                moves_right,
+                mode,
+                puzzle,
             })
         })?
     } else {
@@ -299,6 +466,7 @@ fn get_player_from_java<'local>(
     }))
 }
 
+/// 构造函数的参数顺序见模块文档，改动时需要同步修改 Java 端并递增 [`CONTRACT_VERSION`]
 fn new_player_record<'local>(
     env: &mut JNIEnv<'local>,
     id: i32,
@@ -306,12 +474,25 @@ fn new_player_record<'local>(
 ) -> jni::errors::Result<JObject<'local>> {
     env.new_object(
         "com/smoother/TacticalGrid2048/entity/PlayerRecord",
-        "(IIJJ)V",
+        "(IIJJIJIZIIIIIIIIJ)V",
         &[
             JValueGen::Int(id),
             JValueGen::Int(record.score),
             JValueGen::Long(record.time),
             JValueGen::Long(record.timestamp),
+            JValueGen::Int(record.board_size),
+            JValueGen::Long(record.seed),
+            JValueGen::Int(record.undo_count),
+            JValueGen::Bool(record.won as u8),
+            JValueGen::Int(record.max_tile),
+            JValueGen::Int(record.moves),
+            JValueGen::Int(record.merges),
+            JValueGen::Int(record.moves_up),
+            JValueGen::Int(record.moves_down),
+            JValueGen::Int(record.moves_left),
+            JValueGen::Int(record.moves_right),
+            JValueGen::Int(record.mode),
+            JValueGen::Long(record.puzzle),
         ],
     )
 }
@@ -346,7 +527,7 @@ fn new_player<'local>(
 pub fn get_current_player(
     env: &mut JNIEnv<'_>,
     service: &JObject<'_>,
-) -> jni::errors::Result<Player> {
+) -> jni::errors::Result<Option<Player>> {
     let player: JObject<'_> = env
         .call_method(
             service,
@@ -355,7 +536,7 @@ pub fn get_current_player(
             &[],
         )?
         .try_into()?;
-    get_player_from_java(env, &player).map(|x| x.unwrap_or_default())
+    get_player_from_java(env, &player)
 }
 
 pub fn get_players_best_except_self(
@@ -506,3 +687,59 @@ pub fn remove_player(
     )?
     .z()
 }
+
+pub fn remove_record(
+    env: &mut JNIEnv<'_>,
+    service: &JObject<'_>,
+    player: Player,
+) -> jni::errors::Result<bool> {
+    let player = new_player(env, player)?;
+    env.call_method(
+        service,
+        "removeRecord",
+        "(Lcom/smoother/TacticalGrid2048/entity/Player;)Z",
+        &[JValueGen::Object(&player)],
+    )?
+    .z()
+}
+
+pub fn load_game(
+    env: &mut JNIEnv<'_>,
+    service: &JObject<'_>,
+    player: Player,
+) -> jni::errors::Result<Option<String>> {
+    let player = new_player(env, player)?;
+    let result: JObject<'_> = env
+        .call_method(
+            service,
+            "loadGame",
+            "(Lcom/smoother/TacticalGrid2048/entity/Player;)Ljava/lang/String;",
+            &[JValueGen::Object(&player)],
+        )?
+        .try_into()?;
+    if result.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(env.get_string(&result.into())?.into()))
+}
+
+pub fn save_game(
+    env: &mut JNIEnv<'_>,
+    service: &JObject<'_>,
+    player: Player,
+    game: Option<String>,
+) -> jni::errors::Result<bool> {
+    let player = new_player(env, player)?;
+    // 传 null 表示删除存档
+    let game = match game {
+        Some(game) => env.new_string(game)?.into(),
+        None => JObject::null(),
+    };
+    env.call_method(
+        service,
+        "saveGame",
+        "(Lcom/smoother/TacticalGrid2048/entity/Player;Ljava/lang/String;)Z",
+        &[JValueGen::Object(&player), JValueGen::Object(&game)],
+    )?
+    .z()
+}
@@ -1,40 +1,106 @@
-use crate::app::structs::Player;
+use std::time::Duration;
+
+use crate::app::structs::{Leaderboard, Player};
 
 pub mod jni;
-pub mod dummy;
 
-pub enum TryRecvError {
+/// 请求失败后隔这么久再重试，避免每一帧都弹出同样的提示
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(3);
+
+/// 数据层操作失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataError {
+    /// 请求已经发出，结果还没有返回
     Empty,
     Timeout,
     Disconnected,
+    /// 密码错误
+    AuthFailed,
+    /// 玩家不存在
+    NotFound,
+    /// 与已有的数据冲突，例如重名
+    Conflict(String),
+    /// 提交的数据不合法
+    Validation(String),
+    /// 后端出现异常，附带异常的消息
+    Backend(String),
+}
+
+impl DataError {
+    /// 过一会儿重试可能成功，例如超时；玩家不存在这类错误重试也没有用
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Timeout | Self::Backend(_))
+    }
+
+    /// 提示对话框的标题和内容
+    pub fn describe(&self) -> (&'static str, String) {
+        match self {
+            Self::Empty => (" 请稍候 ", String::from("数据还没有准备好")),
+            Self::Timeout => (
+                " 遇到问题 ",
+                String::from("在处理数据时遇到超时问题，部分操作无法继续"),
+            ),
+            Self::Disconnected => (" 遇到问题 ", String::from("与数据层的连接已经断开")),
+            Self::AuthFailed => (" 登录失败 ", String::from("账号或密码错误")),
+            Self::NotFound => (" 找不到玩家 ", String::from("该玩家不存在，可能已被删除")),
+            Self::Conflict(message) => (" 数据冲突 ", message.clone()),
+            Self::Validation(message) => (" 无效的数据 ", message.clone()),
+            Self::Backend(message) => (" 遇到问题 ", format!("数据层出现异常: {message}")),
+        }
+    }
 }
 
 pub trait DataManager: Send {
     fn is_first_launch(&mut self) -> bool;
 
-    fn verify_account(
-        &mut self,
-        username: String,
-        password: String,
-    ) -> Result<Option<Player>, TryRecvError>;
+    /// 用户不存在时返回 `NotFound`，密码错误时返回 `AuthFailed`
+    fn verify_account(&mut self, username: String, password: String) -> Result<Player, DataError>;
+
+    /// 用户名已被占用时返回 `Conflict`
+    fn register_account(&mut self, username: String, password: String)
+    -> Result<Player, DataError>;
+
+    /// 没有登录，或者登录的玩家已被删除时返回 `NotFound`
+    fn get_current_player(&mut self) -> Result<Player, DataError>;
+
+    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, DataError>;
+
+    fn get_players(&mut self) -> Result<Vec<Player>, DataError>;
 
-    fn register_account(
-        &mut self,
-        username: String,
-        password: String,
-    ) -> Result<Option<Player>, TryRecvError>;
+    /// 排行榜上除当前玩家以外的玩家，每人只带一条不借助撤销的最佳记录，按成绩从高到低排列，
+    /// 成绩相同时按玩家编号排列。最佳记录和排序见 [`Player::best_record`]
+    fn get_leaderboard(&mut self, leaderboard: Leaderboard) -> Result<Vec<Player>, DataError> {
+        let mut players = self
+            .get_players_best_except_self()?
+            .into_iter()
+            .filter_map(|x| {
+                let x = x.filter_records(|r| leaderboard.contains(r) && !r.is_assisted());
+                let best = x.best_record()?.clone();
+                Some(Player {
+                    records: vec![best],
+                    ..x
+                })
+            })
+            .collect::<Vec<_>>();
+        players.sort_by(|a, b| b.cmp(a).then(a.id.cmp(&b.id)));
+        Ok(players)
+    }
 
-    fn get_current_player(&mut self) -> Result<Player, TryRecvError>;
+    fn save_record(&mut self, player: Player) -> Result<(), DataError>;
 
-    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, TryRecvError>;
+    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, DataError>;
 
-    fn get_players(&mut self) -> Result<Vec<Player>, TryRecvError>;
+    /// 只修改玩家名，`records` 被忽略
+    fn update_player(&mut self, player: Player) -> Result<(), DataError>;
 
-    fn save_record(&mut self, player: Player) -> Result<bool, TryRecvError>;
+    /// 删除玩家在 `records` 中列出的记录，按时间戳、分数和种子找出对应的那条
+    fn remove_record(&mut self, player: Player) -> Result<(), DataError>;
 
-    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, TryRecvError>;
+    fn remove_player(&mut self, player: Player) -> Result<(), DataError>;
 
-    fn update_player(&mut self, player: Player) -> Result<bool, TryRecvError>;
+    /// 读取玩家中途退出时保存的对局，没有存档时返回 None
+    fn load_game(&mut self, player: Player) -> Result<Option<String>, DataError>;
 
-    fn remove_player(&mut self, player: Player) -> Result<bool, TryRecvError>;
+    /// 保存玩家中途退出的对局，传入 None 删除存档
+    fn save_game(&mut self, player: Player, game: Option<String>) -> Result<(), DataError>;
 }
@@ -26,25 +26,25 @@ mod macros {
         }};
         ($method:ident, $($params:tt)*) => {{
             let mut binding = $crate::app::entry::DATA_MANAGER.lock().unwrap();
+            // 还在等待结果时返回 None，失败时先弹出对应的提示再返回错误
             match binding.as_mut().unwrap().$method($($params)*) {
-                Ok(x) => Some(x),
-                Err(e) => match e {
-                    $crate::app::data::TryRecvError::Empty => None,
-                    $crate::app::data::TryRecvError::Timeout => {
-                        let mut dialog_manager = $crate::app::screens::dialog::DIALOG_MANAGER.write().unwrap();
-                        dialog_manager.push($crate::app::screens::dialog::Dialog::new(
-                            " 遇到问题 ",
-                            "在处理数据时遇到超时问题，部分操作无法继续",
-                            ratatui::prelude::Alignment::Left,
-                            false,
-                            vec![String::from("确定")],
-                            None,
-                        ));
-                        None
-                    },
-                    $crate::app::data::TryRecvError::Disconnected => {
-                        panic!("Fatal Error: Internal connection is closed");
-                    },
+                Ok(x) => Some(Ok(x)),
+                Err($crate::app::data::DataError::Empty) => None,
+                Err($crate::app::data::DataError::Disconnected) => {
+                    panic!("Fatal Error: Internal connection is closed");
+                }
+                Err(e) => {
+                    let (title, content) = e.describe();
+                    let mut dialog_manager = $crate::app::screens::dialog::DIALOG_MANAGER.write().unwrap();
+                    dialog_manager.push($crate::app::screens::dialog::Dialog::new(
+                        title,
+                        &content,
+                        ratatui::prelude::Alignment::Left,
+                        false,
+                        vec![String::from("确定")],
+                        None,
+                    ));
+                    Some(Err(e))
                 }
             }
         }};
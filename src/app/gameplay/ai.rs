@@ -0,0 +1,285 @@
+use std::sync::LazyLock;
+
+use crate::app::structs::{Cell, CellMotionDirection};
+
+use super::{Grid, bitboard::Bitboard, game::DIRECTIONS, movement, spawn::SpawnPolicy};
+
+// 局面评估的权重，参考了常见的 2048 启发式
+const LOST_PENALTY: f64 = 200000.0;
+const MONOTONICITY_POWER: f64 = 4.0;
+const MONOTONICITY_WEIGHT: f64 = 47.0;
+const SUM_POWER: f64 = 3.5;
+const SUM_WEIGHT: f64 = 11.0;
+const MERGES_WEIGHT: f64 = 700.0;
+const EMPTY_WEIGHT: f64 = 270.0;
+
+/// 累计概率低于该值的分支不再展开
+const PROBABILITY_CUTOFF: f64 = 0.0001;
+
+/// 一条线的评估分数，`line` 中是地块的指数，0 表示空格
+fn line_heuristic(line: &[u8]) -> f64 {
+    let mut sum = 0.0;
+    let mut empty = 0;
+    let mut merges = 0;
+    let mut prev = 0;
+    let mut counter = 0;
+    for &rank in line {
+        sum += (rank as f64).powf(SUM_POWER);
+        if rank == 0 {
+            empty += 1;
+            continue;
+        }
+        if prev == rank {
+            counter += 1;
+        } else if counter > 0 {
+            merges += 1 + counter;
+            counter = 0;
+        }
+        prev = rank;
+    }
+    if counter > 0 {
+        merges += 1 + counter;
+    }
+
+    let mut left = 0.0;
+    let mut right = 0.0;
+    for pair in line.windows(2) {
+        let a = (pair[0] as f64).powf(MONOTONICITY_POWER);
+        let b = (pair[1] as f64).powf(MONOTONICITY_POWER);
+        if pair[0] > pair[1] {
+            left += a - b;
+        } else {
+            right += b - a;
+        }
+    }
+
+    LOST_PENALTY + EMPTY_WEIGHT * empty as f64 + MERGES_WEIGHT * merges as f64
+        - MONOTONICITY_WEIGHT * left.min(right)
+        - SUM_WEIGHT * sum
+}
+
+/// 位棋盘每一行的评估分数
+static ROW_HEURISTIC: LazyLock<Vec<f64>> = LazyLock::new(|| {
+    (0..=u16::MAX)
+        .map(|row| {
+            let line = [0, 1, 2, 3].map(|k| ((row >> (4 * k)) & 0xF) as u8);
+            line_heuristic(&line)
+        })
+        .collect()
+});
+
+/// 搜索用的局面
+pub trait Position: Sized {
+    /// 移动后的局面，不改变地块时返回 `None`
+    fn swipe(&self, direction: CellMotionDirection) -> Option<Self>;
+
+    fn empty_cells(&self) -> Vec<usize>;
+
+    /// 在 `index` 处放一个指数为 `exponent` 的地块
+    fn place(&self, index: usize, exponent: u8) -> Self;
+
+    fn evaluate(&self) -> f64;
+}
+
+impl Position for Bitboard {
+    fn swipe(&self, direction: CellMotionDirection) -> Option<Self> {
+        let (board, _) = Bitboard::swipe(*self, direction)?;
+        (board != *self).then_some(board)
+    }
+
+    fn empty_cells(&self) -> Vec<usize> {
+        (0..16).filter(|k| (self.0 >> (4 * k)) & 0xF == 0).collect()
+    }
+
+    fn place(&self, index: usize, exponent: u8) -> Self {
+        Self(self.0 | (exponent as u64) << (4 * index))
+    }
+
+    fn evaluate(&self) -> f64 {
+        let transposed = self.transposed();
+        (0..4)
+            .map(|x| {
+                ROW_HEURISTIC[self.row(x) as usize] + ROW_HEURISTIC[transposed.row(x) as usize]
+            })
+            .sum()
+    }
+}
+
+impl Position for Grid {
+    fn swipe(&self, direction: CellMotionDirection) -> Option<Self> {
+        let mut cells = self.clone();
+        let (animations, _) = movement::swipe(&mut cells, direction);
+        (!animations.is_empty()).then_some(cells)
+    }
+
+    fn empty_cells(&self) -> Vec<usize> {
+        let size = self.len();
+        (0..size * size)
+            .filter(|x| self[x / size][x % size].empty())
+            .collect()
+    }
+
+    fn place(&self, index: usize, exponent: u8) -> Self {
+        let size = self.len();
+        let mut cells = self.clone();
+        cells[index / size][index % size] = Cell::new(exponent);
+        cells
+    }
+
+    fn evaluate(&self) -> f64 {
+        let size = self.len();
+        let rows = self
+            .iter()
+            .map(|row| line_heuristic(&row.iter().map(Cell::exponent).collect::<Vec<_>>()));
+        let cols = (0..size)
+            .map(|j| line_heuristic(&self.iter().map(|row| row[j].exponent()).collect::<Vec<_>>()));
+        rows.chain(cols).sum()
+    }
+}
+
+/// AI 给出的建议
+#[derive(Debug, Clone, Copy)]
+pub struct Hint {
+    pub direction: CellMotionDirection,
+    /// 最佳方向的期望评估分数
+    pub evaluation: f64,
+    /// 最佳方向比次佳方向好多少，0 到 1 之间，只有一个方向可走时为 1
+    pub margin: f64,
+}
+
+/// 期望最大化搜索：玩家选择期望最高的方向，新地块按生成概率取期望
+pub struct Expectimax {
+    /// 新地块的指数和概率
+    spawn: Vec<(u8, f64)>,
+    depth: usize,
+}
+
+impl Expectimax {
+    pub fn new(spawn: &dyn SpawnPolicy, depth: usize) -> Self {
+        Self {
+            spawn: spawn
+                .distribution()
+                .into_iter()
+                .filter(|(exponent, p)| *exponent > 0 && *p > 0.0)
+                .collect(),
+            depth,
+        }
+    }
+
+    /// 每个可走方向的期望评估分数
+    pub fn evaluate_moves<P: Position>(&self, position: &P) -> Vec<(CellMotionDirection, f64)> {
+        DIRECTIONS
+            .into_iter()
+            .filter_map(|x| {
+                let next = position.swipe(x)?;
+                Some((x, self.chance_node(&next, self.depth, 1.0)))
+            })
+            .collect()
+    }
+
+    pub fn best_move<P: Position>(&self, position: &P) -> Option<Hint> {
+        let mut moves = self.evaluate_moves(position);
+        moves.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let (direction, evaluation) = *moves.first()?;
+        let margin = moves.get(1).map_or(1.0, |(_, second)| {
+            if evaluation > 0.0 {
+                ((evaluation - second) / evaluation).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        });
+        Some(Hint {
+            direction,
+            evaluation,
+            margin,
+        })
+    }
+
+    fn max_node<P: Position>(&self, position: &P, depth: usize, probability: f64) -> f64 {
+        DIRECTIONS
+            .into_iter()
+            .filter_map(|x| position.swipe(x))
+            .map(|x| self.chance_node(&x, depth, probability))
+            .fold(0.0, f64::max)
+    }
+
+    fn chance_node<P: Position>(&self, position: &P, depth: usize, probability: f64) -> f64 {
+        if depth == 0 || probability < PROBABILITY_CUTOFF {
+            return position.evaluate();
+        }
+        let empty = position.empty_cells();
+        if empty.is_empty() || self.spawn.is_empty() {
+            return position.evaluate();
+        }
+        let cell_probability = probability / empty.len() as f64;
+        let mut total = 0.0;
+        for index in &empty {
+            for (exponent, p) in &self.spawn {
+                let next = position.place(*index, *exponent);
+                total += p * self.max_node(&next, depth - 1, cell_probability * p);
+            }
+        }
+        total / empty.len() as f64
+    }
+}
+
+/// 为当前局面找出最佳方向，空格越少搜索得越深
+pub fn hint(cells: &Grid, spawn: &dyn SpawnPolicy) -> Option<Hint> {
+    let depth = |empty: usize| if empty > 6 { 2 } else { 3 };
+    match Bitboard::from_grid(cells) {
+        Some(board) => Expectimax::new(spawn, depth(board.empty_cells().len())).best_move(&board),
+        // 普通棋盘每一步都要复制，少搜一层
+        None => Expectimax::new(spawn, depth(cells.empty_cells().len()) - 1).best_move(cells),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::gameplay::spawn::StandardSpawn;
+
+    /// 2 和 4 交错排列，没有可以合并或移动的地块
+    fn checkerboard(size: usize) -> Grid {
+        (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| Cell::new(1 + ((i + j) % 2) as u8))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merges_into_the_corner() {
+        // 只有左上角的两个 1024 能横向合并，向左合并后 2048 留在角上
+        let mut cells = checkerboard(4);
+        cells[0][0] = Cell::new(10);
+        cells[0][1] = Cell::new(10);
+        let spawn = StandardSpawn::default();
+
+        let search = Expectimax::new(&spawn, 1);
+        let directions = search
+            .evaluate_moves(&cells)
+            .into_iter()
+            .map(|(x, _)| x)
+            .collect::<Vec<_>>();
+        assert_eq!(directions.len(), 2);
+        assert!(directions.contains(&CellMotionDirection::Left));
+        assert!(directions.contains(&CellMotionDirection::Right));
+
+        let hint = search.best_move(&cells).unwrap();
+        assert_eq!(hint.direction, CellMotionDirection::Left);
+        // 位棋盘上的搜索给出同样的方向
+        let hint = super::hint(&cells, &spawn).unwrap();
+        assert_eq!(hint.direction, CellMotionDirection::Left);
+        assert!((0.0..=1.0).contains(&hint.margin));
+    }
+
+    #[test]
+    fn no_hint_when_the_game_is_over() {
+        let spawn = StandardSpawn::default();
+        for size in [3, 4, 5] {
+            assert!(hint(&checkerboard(size), &spawn).is_none(), "{size}");
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::{sync::LazyLock, time::Duration};
+
+use crate::app::structs::{AnimationCell, Cell, CellAnimationType, CellMotionDirection};
+
+use super::{Grid, movement::line_coord};
+
+/// 位棋盘只支持 4×4
+pub const BITBOARD_SIZE: usize = 4;
+
+/// 一个 4 位的格子能表示的最大指数，即 32768
+const MAX_EXPONENT: u8 = 15;
+
+/// 一行地块向下标 0 一侧移动的结果
+#[derive(Clone, Copy, Default)]
+struct RowMove {
+    row: u16,
+    /// 与 `swipe_line` 一致，只保留最后一次合并的分数
+    score: u16,
+    /// 每个地块的移动：(起点, 终点, 指数)
+    moves: [(u8, u8, u8); 3],
+    len: u8,
+    /// 合并会超出 32768，位棋盘无法表示
+    overflow: bool,
+}
+
+/// 所有 65536 种行的移动结果，按 `swipe_line` 的规则逐格模拟得到
+static ROW_TABLE: LazyLock<Vec<RowMove>> = LazyLock::new(|| (0..=u16::MAX).map(row_move).collect());
+
+fn row_move(row: u16) -> RowMove {
+    let mut cells = [0_u8; 4];
+    for (k, cell) in cells.iter_mut().enumerate() {
+        *cell = ((row >> (4 * k)) & 0xF) as u8;
+    }
+
+    let mut entry = RowMove::default();
+    for i in 1..cells.len() {
+        let exponent = cells[i];
+        if exponent == 0 {
+            continue;
+        }
+
+        let mut temp = i - 1;
+        while temp > 0 && cells[temp] == 0 {
+            temp -= 1;
+        }
+
+        let dest = if cells[temp] == exponent {
+            if exponent >= MAX_EXPONENT {
+                entry.overflow = true;
+                return entry;
+            }
+            cells[temp] += 1;
+            entry.score = 1 << cells[temp];
+            temp
+        } else if cells[temp] == 0 {
+            cells[temp] = exponent;
+            temp
+        } else if temp + 1 != i {
+            cells[temp + 1] = exponent;
+            temp + 1
+        } else {
+            continue;
+        };
+        cells[i] = 0;
+        entry.moves[entry.len as usize] = (i as u8, dest as u8, exponent);
+        entry.len += 1;
+    }
+
+    entry.row = cells
+        .iter()
+        .enumerate()
+        .fold(0, |acc, (k, x)| acc | (*x as u16) << (4 * k));
+    entry
+}
+
+fn reverse_row(row: u16) -> u16 {
+    (row >> 12) | ((row >> 4) & 0x00F0) | ((row << 4) & 0x0F00) | (row << 12)
+}
+
+/// 行列互换，`(x, y)` 处的格子换到 `(y, x)`
+fn transpose(board: u64) -> u64 {
+    let a1 = board & 0xF0F0_0F0F_F0F0_0F0F;
+    let a2 = board & 0x0000_F0F0_0000_F0F0;
+    let a3 = board & 0x0F0F_0000_0F0F_0000;
+    let a = a1 | (a2 << 12) | (a3 >> 12);
+    let b1 = a & 0xFF00_FF00_00FF_00FF;
+    let b2 = a & 0x00FF_00FF_0000_0000;
+    let b3 = a & 0x0000_0000_FF00_FF00;
+    b1 | (b2 >> 24) | (b3 << 24)
+}
+
+/// 4×4 棋盘的 64 位表示，每格 4 位保存地块的指数，0 表示空格
+///
+/// `(x, y)` 位于第 `4 * x + y` 个 4 位
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    /// 棋盘不是 4×4 或者有超过 32768 的地块时返回 `None`
+    pub fn from_grid(cells: &Grid) -> Option<Self> {
+        if cells.len() != BITBOARD_SIZE || cells.iter().any(|x| x.len() != BITBOARD_SIZE) {
+            return None;
+        }
+        let mut board = 0;
+        for (i, row) in cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                let exponent = cell.exponent();
+                if exponent > MAX_EXPONENT {
+                    return None;
+                }
+                board |= (exponent as u64) << (4 * (BITBOARD_SIZE * i + j));
+            }
+        }
+        Some(Self(board))
+    }
+
+    pub fn write_grid(self, cells: &mut Grid) {
+        for (i, row) in cells.iter_mut().enumerate() {
+            for (j, cell) in row.iter_mut().enumerate() {
+                *cell = Cell::new(self.exponent(i, j));
+            }
+        }
+    }
+
+    /// 第 `x` 行的 16 位
+    pub fn row(self, x: usize) -> u16 {
+        (self.0 >> (16 * x)) as u16
+    }
+
+    pub fn transposed(self) -> Self {
+        Self(transpose(self.0))
+    }
+
+    pub fn exponent(self, x: usize, y: usize) -> u8 {
+        ((self.0 >> (4 * (BITBOARD_SIZE * x + y))) & 0xF) as u8
+    }
+
+    /// 只计算移动后的棋盘和分数，合并超出 32768 时返回 `None`
+    pub fn swipe(self, direction: CellMotionDirection) -> Option<(Self, u64)> {
+        self.swipe_with(direction, None)
+    }
+
+    /// 与 `movement::move_*` 结果一致，包括动画的顺序
+    pub fn swipe_animated(
+        self,
+        direction: CellMotionDirection,
+    ) -> Option<(Self, Vec<AnimationCell>, u64)> {
+        let mut animations = Vec::new();
+        let (board, score) = self.swipe_with(direction, Some(&mut animations))?;
+        Some((board, animations, score))
+    }
+
+    /// 该方向能否让地块改变
+    pub fn can_move(self, direction: CellMotionDirection) -> bool {
+        self.swipe(direction).is_none_or(|(x, _)| x != self)
+    }
+
+    fn swipe_with(
+        self,
+        direction: CellMotionDirection,
+        mut animations: Option<&mut Vec<AnimationCell>>,
+    ) -> Option<(Self, u64)> {
+        let transposed = matches!(
+            direction,
+            CellMotionDirection::Up | CellMotionDirection::Down
+        );
+        let reversed = matches!(
+            direction,
+            CellMotionDirection::Down | CellMotionDirection::Right
+        );
+        let board = if transposed {
+            transpose(self.0)
+        } else {
+            self.0
+        };
+
+        let mut result = 0;
+        let mut score = 0_u64;
+        for i in 0..BITBOARD_SIZE {
+            let mut row = (board >> (16 * i)) as u16;
+            if reversed {
+                row = reverse_row(row);
+            }
+            let entry = &ROW_TABLE[row as usize];
+            if entry.overflow {
+                return None;
+            }
+            let row = if reversed {
+                reverse_row(entry.row)
+            } else {
+                entry.row
+            };
+            result |= (row as u64) << (16 * i);
+            score += entry.score as u64;
+
+            if let Some(ref mut animations) = animations {
+                for (src, dest, exponent) in &entry.moves[..entry.len as usize] {
+                    animations.push(AnimationCell {
+                        src: line_coord(direction, i, BITBOARD_SIZE, *src as usize),
+                        cell: Cell::new(*exponent),
+                        animation_type: CellAnimationType::Move,
+                        dest: Some(line_coord(direction, i, BITBOARD_SIZE, *dest as usize)),
+                        duration: Duration::default(),
+                    });
+                }
+            }
+        }
+
+        let result = if transposed {
+            transpose(result)
+        } else {
+            result
+        };
+        Some((Self(result), score))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::app::gameplay::{
+        GameRng,
+        game::DIRECTIONS,
+        movement::{self, move_down, move_left, move_right, move_up},
+    };
+
+    /// 逐行移动的实现，位棋盘需要与它完全一致
+    fn reference(cells: &mut Grid, direction: CellMotionDirection) -> (Vec<AnimationCell>, u64) {
+        match direction {
+            CellMotionDirection::Up => move_up(cells),
+            CellMotionDirection::Down => move_down(cells),
+            CellMotionDirection::Left => move_left(cells),
+            CellMotionDirection::Right => move_right(cells),
+        }
+    }
+
+    type Motion = ((usize, usize), Option<(usize, usize)>, u8);
+
+    fn motions(animations: &[AnimationCell]) -> Vec<Motion> {
+        animations
+            .iter()
+            .map(|x| {
+                assert!(x.animation_type == CellAnimationType::Move);
+                (
+                    (x.src.x, x.src.y),
+                    x.dest.map(|x| (x.x, x.y)),
+                    x.cell.exponent(),
+                )
+            })
+            .collect()
+    }
+
+    fn grid(exponents: [[u8; 4]; 4]) -> Grid {
+        exponents
+            .iter()
+            .map(|row| row.iter().map(|x| Cell::new(*x)).collect())
+            .collect()
+    }
+
+    fn random_grid(rng: &mut GameRng) -> Grid {
+        let mut exponents = [[0; 4]; 4];
+        for x in exponents.iter_mut().flatten() {
+            // 一半左右是空格，其余的指数偏小，容易出现合并
+            let max = rng.gen_range(2..=MAX_EXPONENT);
+            *x = if rng.gen_bool(0.5) {
+                0
+            } else {
+                rng.gen_range(1..=max)
+            };
+        }
+        grid(exponents)
+    }
+
+    fn check(cells: &Grid) {
+        let board = Bitboard::from_grid(cells).unwrap();
+        for direction in DIRECTIONS {
+            let mut expected = cells.clone();
+            let (animations, score) = reference(&mut expected, direction);
+            let changed = expected != *cells;
+
+            match board.swipe_animated(direction) {
+                Some((result, bit_animations, bit_score)) => {
+                    let mut actual = cells.clone();
+                    result.write_grid(&mut actual);
+                    assert!(actual == expected, "{direction:?} {:016x}", board.0);
+                    assert_eq!(bit_score, score, "{direction:?} {:016x}", board.0);
+                    assert_eq!(motions(&bit_animations), motions(&animations));
+                    assert_eq!(board.swipe(direction), Some((result, score)));
+                }
+                // 只有合成超过 32768 时才交给逐行移动
+                None => assert!(
+                    expected
+                        .iter()
+                        .flatten()
+                        .any(|x| x.exponent() > MAX_EXPONENT)
+                ),
+            }
+            assert_eq!(
+                board.can_move(direction),
+                changed,
+                "{direction:?} {:016x}",
+                board.0
+            );
+
+            let mut swiped = cells.clone();
+            let (swiped_animations, swiped_score) = movement::swipe(&mut swiped, direction);
+            assert!(swiped == expected);
+            assert_eq!(swiped_score, score);
+            assert_eq!(motions(&swiped_animations), motions(&animations));
+        }
+    }
+
+    #[test]
+    fn matches_movement_on_random_boards() {
+        let mut rng = GameRng::seed_from_u64(2048);
+        for _ in 0..20000 {
+            check(&random_grid(&mut rng));
+        }
+    }
+
+    #[test]
+    fn every_row_matches_movement() {
+        for row in 0..=u16::MAX {
+            let exponents = [0, 4, 8, 12].map(|k| ((row >> k) & 0xF) as u8);
+            check(&grid([exponents, [0; 4], [0; 4], [0; 4]]));
+        }
+    }
+
+    #[test]
+    fn overflow_falls_back_to_movement() {
+        let cells = grid([[15, 15, 0, 0], [1, 2, 3, 4], [4, 3, 2, 1], [1, 2, 3, 4]]);
+        let board = Bitboard::from_grid(&cells).unwrap();
+        assert_eq!(board.swipe(CellMotionDirection::Left), None);
+        assert!(board.swipe_animated(CellMotionDirection::Right).is_none());
+        assert!(board.can_move(CellMotionDirection::Left));
+
+        let mut swiped = cells.clone();
+        let (_, score) = movement::swipe(&mut swiped, CellMotionDirection::Left);
+        assert_eq!(swiped[0][0].exponent(), 16);
+        assert_eq!(score, 1 << 16);
+        check(&cells);
+
+        // 已经超过 32768 的地块无法放进位棋盘
+        assert!(Bitboard::from_grid(&swiped).is_none());
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let mut rng = GameRng::seed_from_u64(0);
+        for _ in 0..1000 {
+            let board = Bitboard(rng.r#gen());
+            let transposed = board.transposed();
+            for x in 0..BITBOARD_SIZE {
+                for y in 0..BITBOARD_SIZE {
+                    assert_eq!(transposed.exponent(y, x), board.exponent(x, y));
+                }
+            }
+            assert_eq!(transposed.transposed(), board);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use rand::{SeedableRng, seq::SliceRandom};
+
+use crate::app::structs::CellMotionDirection;
+
+use super::{
+    GameRng, Grid, ai, apply_move, check_game_over,
+    game::{DIRECTIONS, Game},
+    movement,
+};
+
+/// 自动游玩的策略，新的机器人实现这个 trait 并加入 [`STRATEGIES`]
+pub trait Strategy: Send {
+    /// 选择下一步，没有可走的方向时返回 `None`
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection>;
+}
+
+/// 根据本局的种子创建策略
+pub type StrategyBuilder = fn(u64) -> Box<dyn Strategy>;
+
+/// 可供选择的策略：名称和构造函数
+pub const STRATEGIES: [(&str, StrategyBuilder); 5] = [
+    ("随机", |seed| Box::new(RandomStrategy::new(seed))),
+    ("贪心", |_| Box::new(GreedyStrategy)),
+    ("角落", |_| Box::new(CornerStrategy)),
+    ("期望最大", |_| Box::new(ExpectimaxStrategy)),
+    ("蒙特卡洛", |seed| {
+        Box::new(MonteCarloStrategy::new(seed))
+    }),
+];
+
+/// 随便选一个能走的方向
+pub struct RandomStrategy {
+    rng: GameRng,
+}
+
+impl RandomStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: GameRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Strategy for RandomStrategy {
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection> {
+        game.legal_moves().choose(&mut self.rng).copied()
+    }
+}
+
+/// 选择立即得分最多的方向，分数相同时选择留下空格最多的
+pub struct GreedyStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection> {
+        game.legal_moves().into_iter().max_by_key(|x| {
+            let mut cells = game.cells().clone();
+            let (_, score) = movement::swipe(&mut cells, *x);
+            (score, empty_count(&cells))
+        })
+    }
+}
+
+/// 把大的地块堆在左下角：优先向下，其次向左、向右，实在不行才向上
+pub struct CornerStrategy;
+
+impl Strategy for CornerStrategy {
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection> {
+        let legal = game.legal_moves();
+        [
+            CellMotionDirection::Down,
+            CellMotionDirection::Left,
+            CellMotionDirection::Right,
+            CellMotionDirection::Up,
+        ]
+        .into_iter()
+        .find(|x| legal.contains(x))
+    }
+}
+
+/// 与游戏中的 AI 提示相同
+pub struct ExpectimaxStrategy;
+
+impl Strategy for ExpectimaxStrategy {
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection> {
+        ai::hint(game.cells(), game.config().spawn.as_ref()).map(|x| x.direction)
+    }
+}
+
+/// 蒙特卡洛模拟结束时每个空格折算的分数
+const EMPTY_BONUS: u64 = 10;
+
+/// 每个方向随机模拟若干局，选择平均得分最高的
+pub struct MonteCarloStrategy {
+    rng: GameRng,
+    rollouts: usize,
+    /// 每次模拟最多走的步数
+    depth: usize,
+}
+
+impl MonteCarloStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: GameRng::seed_from_u64(seed),
+            rollouts: 40,
+            depth: 30,
+        }
+    }
+
+    fn rollout(&mut self, game: &Game, first: CellMotionDirection) -> u64 {
+        let spawn = game.config().spawn.as_ref();
+        let mut cells = game.cells().clone();
+        let (_, score) = apply_move(&mut cells, first, spawn, &mut self.rng);
+        let mut total = score;
+        let mut directions = DIRECTIONS;
+        for _ in 0..self.depth {
+            if check_game_over(&mut cells) {
+                break;
+            }
+            directions.shuffle(&mut self.rng);
+            for direction in directions {
+                let (animations, score) = apply_move(&mut cells, direction, spawn, &mut self.rng);
+                if !animations.is_empty() {
+                    total = total.saturating_add(score);
+                    break;
+                }
+            }
+        }
+        // 剩下的空格越多越好
+        total.saturating_add(empty_count(&cells) as u64 * EMPTY_BONUS)
+    }
+}
+
+impl Strategy for MonteCarloStrategy {
+    fn choose(&mut self, game: &Game) -> Option<CellMotionDirection> {
+        game.legal_moves().into_iter().max_by_key(|x| {
+            (0..self.rollouts).fold(0_u64, |acc, _| acc.saturating_add(self.rollout(game, *x)))
+        })
+    }
+}
+
+fn empty_count(cells: &Grid) -> usize {
+    cells.iter().flatten().filter(|x| x.empty()).count()
+}
@@ -15,19 +15,20 @@ pub fn brightness(col: Color) -> f32 {
     }
 }
 
-pub fn color_setter(value: u16) -> Color {
-    match value {
+/// 按地块的指数取颜色，0 为空格
+pub fn color_setter(exponent: u8) -> Color {
+    match exponent {
         0 => rgb(44, 58, 71),
-        2 => rgb(238, 228, 218),
-        4 => rgb(237, 224, 200),
-        8 => rgb(242, 177, 121),
-        16 => rgb(245, 149, 99),
-        32 => rgb(246, 124, 96),
-        64 => rgb(246, 94, 59),
-        128 => rgb(237, 207, 114),
-        256 => rgb(237, 204, 97),
-        512 => rgb(237, 200, 80),
-        1024 => rgb(237, 197, 63),
+        1 => rgb(238, 228, 218),
+        2 => rgb(237, 224, 200),
+        3 => rgb(242, 177, 121),
+        4 => rgb(245, 149, 99),
+        5 => rgb(246, 124, 96),
+        6 => rgb(246, 94, 59),
+        7 => rgb(237, 207, 114),
+        8 => rgb(237, 204, 97),
+        9 => rgb(237, 200, 80),
+        10 => rgb(237, 197, 63),
         _ => rgb(237, 194, 46),
     }
 }
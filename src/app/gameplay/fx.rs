@@ -8,3 +8,10 @@ pub fn gen_matrix(duration: Duration) -> Rain {
         .with_head_color(tailwind::LIME.c100)
         .with_color(tailwind::LIME.c400)
 }
+
+/// 限时模式最后几秒的警示效果
+pub fn gen_alarm(duration: Duration) -> Rain {
+    Rain::new_matrix(duration)
+        .with_head_color(tailwind::RED.c100)
+        .with_color(tailwind::RED.c500)
+}
@@ -0,0 +1,450 @@
+use std::time::Duration;
+
+use rand::SeedableRng;
+
+use crate::app::structs::{AnimationCell, Cell, CellAnimationType, CellMotionDirection, Vec2};
+
+use super::{
+    GameConfig, GameRng, Grid, apply_move,
+    bitboard::Bitboard,
+    check_game_over,
+    history::{History, Snapshot},
+    max_tile, movement, new_grid, start_up,
+};
+
+pub const DIRECTIONS: [CellMotionDirection; 4] = [
+    CellMotionDirection::Up,
+    CellMotionDirection::Down,
+    CellMotionDirection::Left,
+    CellMotionDirection::Right,
+];
+
+/// 一次移动的结果
+#[derive(Clone, Default)]
+pub struct MoveOutcome {
+    /// 地块是否改变过，没有改变时不会生成新地块
+    pub moved: bool,
+    /// 本次移动得到的分数
+    pub score: u64,
+    pub animations: Vec<AnimationCell>,
+    pub game_over: bool,
+    /// 本次移动第一次合成了目标地块
+    pub won: bool,
+}
+
+/// 一局中玩家操作的统计，撤销不会减少已经统计的数量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameStats {
+    /// 各方向有效移动的次数，顺序与 `DIRECTIONS` 相同
+    pub moves: [u32; 4],
+    /// 地块合并的次数
+    pub merges: u32,
+}
+
+impl GameStats {
+    /// 有效移动的总次数
+    pub fn total_moves(&self) -> u32 {
+        self.moves.iter().sum()
+    }
+
+    pub fn moves_towards(&self, direction: CellMotionDirection) -> u32 {
+        self.moves[direction_index(direction)]
+    }
+
+    fn record(&mut self, direction: CellMotionDirection, merges: u32) {
+        self.moves[direction_index(direction)] += 1;
+        self.merges += merges;
+    }
+}
+
+fn direction_index(direction: CellMotionDirection) -> usize {
+    DIRECTIONS
+        .iter()
+        .position(|x| *x == direction)
+        .unwrap_or_default()
+}
+
+/// 一局游戏的规则和状态，不依赖界面和全局时间
+///
+/// 同样的 `config`、`seed` 和操作序列总是得到同样的对局
+#[derive(Clone)]
+pub struct Game {
+    config: GameConfig,
+    seed: u64,
+    cells: Grid,
+    rng: GameRng,
+    score: u64,
+    history: History,
+    over: bool,
+    /// 合成过目标地块，撤销也不会取消
+    won: bool,
+    stats: GameStats,
+}
+
+impl Game {
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let mut cells = new_grid(config.board_size);
+        start_up(&mut cells, &config, &mut rng);
+        Self {
+            history: History::new(config.undo_budget),
+            config,
+            seed,
+            cells,
+            rng,
+            score: 0,
+            over: false,
+            won: false,
+            stats: GameStats::default(),
+        }
+    }
+
+    /// 从给定的局面开局，不再放置开局地块
+    pub fn from_cells(config: GameConfig, seed: u64, mut cells: Grid) -> Self {
+        Self {
+            history: History::new(config.undo_budget),
+            over: check_game_over(&mut cells),
+            config,
+            seed,
+            cells,
+            rng: GameRng::seed_from_u64(seed),
+            score: 0,
+            won: false,
+            stats: GameStats::default(),
+        }
+    }
+
+    /// 从存档恢复一局，`state` 是当前局面
+    pub fn resume(
+        config: GameConfig,
+        seed: u64,
+        state: Snapshot,
+        history: History,
+        won: bool,
+        stats: GameStats,
+    ) -> Self {
+        let Snapshot {
+            mut cells,
+            rng,
+            score,
+        } = state;
+        let mut this = Self {
+            over: check_game_over(&mut cells),
+            config,
+            seed,
+            cells,
+            rng,
+            score,
+            history,
+            won,
+            stats,
+        };
+        this.over |= this.moves_left() == Some(0);
+        this
+    }
+
+    /// 不带撤销记录的副本，给机器人和搜索使用
+    pub fn fork(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            seed: self.seed,
+            cells: self.cells.clone(),
+            rng: self.rng.clone(),
+            score: self.score,
+            history: History::new(self.config.undo_budget),
+            over: self.over,
+            won: self.won,
+            stats: self.stats.clone(),
+        }
+    }
+
+    pub fn apply(&mut self, direction: CellMotionDirection) -> MoveOutcome {
+        if self.over {
+            return MoveOutcome {
+                game_over: true,
+                ..Default::default()
+            };
+        }
+        let before = self.snapshot();
+        let (animations, score) = apply_move(
+            &mut self.cells,
+            direction,
+            self.config.spawn.as_ref(),
+            &mut self.rng,
+        );
+        let moved = !animations.is_empty();
+        if moved {
+            // 合并掉的地块数 = 移动前的地块 + 新生成的地块 - 移动后的地块
+            let spawned = animations
+                .iter()
+                .filter(|x| x.animation_type == CellAnimationType::Popup)
+                .count();
+            let merges =
+                (tile_count(&before.cells) + spawned).saturating_sub(tile_count(&self.cells));
+            self.stats.record(direction, merges as u32);
+            self.history.record(before);
+        }
+        self.score = self.score.saturating_add(score);
+        self.over = check_game_over(&mut self.cells) || self.moves_left() == Some(0);
+        let won = !self.won && self.reached_target();
+        self.won |= won;
+        MoveOutcome {
+            moved,
+            score,
+            animations,
+            game_over: self.over,
+            won,
+        }
+    }
+
+    fn reached_target(&self) -> bool {
+        self.config.target_tile != 0 && self.max_tile().value() >= self.config.target_tile
+    }
+
+    /// 玩家在获胜后选择不再继续，本局就此结束
+    pub fn finish(&mut self) {
+        self.over = true;
+    }
+
+    /// 能让地块改变的方向
+    pub fn legal_moves(&self) -> Vec<CellMotionDirection> {
+        if let Some(board) = Bitboard::from_grid(&self.cells) {
+            return DIRECTIONS
+                .into_iter()
+                .filter(|x| board.can_move(*x))
+                .collect();
+        }
+        DIRECTIONS
+            .into_iter()
+            .filter(|x| {
+                let mut cells = self.cells.clone();
+                !movement::swipe(&mut cells, *x).0.is_empty()
+            })
+            .collect()
+    }
+
+    /// 撤销一步，没有可撤销的步骤或次数用尽时返回 `false`
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.history.undo(self.snapshot()) else {
+            return false;
+        };
+        self.restore(snapshot);
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.history.redo(self.snapshot()) else {
+            return false;
+        };
+        self.restore(snapshot);
+        true
+    }
+
+    /// 当前的局面
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cells: self.cells.clone(),
+            rng: self.rng.clone(),
+            score: self.score,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.cells = snapshot.cells;
+        self.rng = snapshot.rng;
+        self.score = snapshot.score;
+        self.over = check_game_over(&mut self.cells) || self.moves_left() == Some(0);
+    }
+
+    /// 当前局面上所有地块的出现动画，用于开局或跳转后的显示
+    pub fn popup_animations(&self) -> Vec<AnimationCell> {
+        let mut animations = Vec::new();
+        for (i, row) in self.cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if cell.empty() {
+                    continue;
+                }
+                animations.push(AnimationCell {
+                    src: Vec2 { x: i, y: j },
+                    animation_type: CellAnimationType::Popup,
+                    dest: None,
+                    cell: *cell,
+                    duration: Duration::default(),
+                });
+            }
+        }
+        animations
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn has_won(&self) -> bool {
+        self.won
+    }
+
+    pub fn max_tile(&self) -> Cell {
+        max_tile(&self.cells)
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn cells(&self) -> &Grid {
+        &self.cells
+    }
+
+    pub fn board_size(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// 本局已经撤销过的次数
+    pub fn undo_used(&self) -> usize {
+        self.history.used()
+    }
+
+    /// 限步模式剩余的移动次数，撤销不会退还已经用掉的次数
+    pub fn moves_left(&self) -> Option<u32> {
+        self.config
+            .mode
+            .move_limit()
+            .map(|x| x.saturating_sub(self.stats.total_moves()))
+    }
+
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn undo_remaining(&self) -> Option<usize> {
+        self.history.remaining()
+    }
+}
+
+fn tile_count(cells: &Grid) -> usize {
+    cells.iter().flatten().filter(|x| !x.empty()).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVES: [CellMotionDirection; 8] = [
+        CellMotionDirection::Left,
+        CellMotionDirection::Up,
+        CellMotionDirection::Right,
+        CellMotionDirection::Up,
+        CellMotionDirection::Down,
+        CellMotionDirection::Left,
+        CellMotionDirection::Up,
+        CellMotionDirection::Right,
+    ];
+
+    fn config() -> GameConfig {
+        GameConfig {
+            undo_budget: None,
+            ..Default::default()
+        }
+    }
+
+    fn play(game: &mut Game, moves: &[CellMotionDirection]) {
+        for direction in moves {
+            game.apply(*direction);
+        }
+    }
+
+    /// 撤销不会减少统计的数量，这里只比较局面和分数
+    fn same(a: &Game, b: &Game) -> bool {
+        a.cells() == b.cells() && a.score() == b.score()
+    }
+
+    #[test]
+    fn same_seed_same_game() {
+        for seed in 0..20 {
+            let mut a = Game::new(config(), seed);
+            let mut b = Game::new(config(), seed);
+            assert!(same(&a, &b));
+            play(&mut a, &MOVES.repeat(10));
+            play(&mut b, &MOVES.repeat(10));
+            assert!(same(&a, &b), "seed {seed}");
+            assert_eq!(a.stats(), b.stats());
+        }
+    }
+
+    #[test]
+    fn seeds_change_the_opening() {
+        let opening = Game::new(config(), 0);
+        assert!((1..20).any(|seed| Game::new(config(), seed).cells() != opening.cells()));
+    }
+
+    #[test]
+    fn undo_restores_the_spawn_rng() {
+        for seed in 0..20 {
+            let mut game = Game::new(config(), seed);
+            play(&mut game, &MOVES);
+            let before = game.fork();
+            let direction = game.legal_moves()[0];
+            game.apply(direction);
+            let after = game.fork();
+
+            assert!(game.undo());
+            assert!(same(&game, &before));
+            // 撤销后再走同一步，新地块和原来的一样
+            game.apply(direction);
+            assert!(same(&game, &after));
+
+            assert!(game.undo());
+            assert!(game.redo());
+            assert!(same(&game, &after));
+        }
+    }
+
+    #[test]
+    fn fork_plays_like_the_original() {
+        let mut game = Game::new(config(), 42);
+        play(&mut game, &MOVES);
+        let mut fork = game.fork();
+        assert!(!fork.undo());
+        play(&mut game, &MOVES.repeat(5));
+        play(&mut fork, &MOVES.repeat(5));
+        assert!(same(&game, &fork));
+    }
+
+    #[test]
+    fn outcome_reports_score_and_moves() {
+        let mut game = Game::new(config(), 7);
+        let mut total = 0;
+        for direction in MOVES.repeat(20) {
+            let moved = game.legal_moves().contains(&direction);
+            let outcome = game.apply(direction);
+            assert_eq!(outcome.moved, moved);
+            total += outcome.score;
+        }
+        assert_eq!(total, game.score());
+    }
+
+    #[test]
+    fn undo_budget_is_enforced() {
+        let mut game = Game::new(GameConfig::default(), 3);
+        play(&mut game, &MOVES.repeat(3));
+        let budget = GameConfig::default().undo_budget.unwrap();
+        for _ in 0..budget {
+            assert!(game.undo());
+        }
+        assert!(!game.undo());
+        assert_eq!(game.undo_used(), budget);
+    }
+}
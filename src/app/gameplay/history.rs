@@ -0,0 +1,151 @@
+use super::{GameRng, Grid};
+
+/// 某一步之前的完整局面，撤销时原样恢复
+#[derive(Clone)]
+pub struct Snapshot {
+    pub cells: Grid,
+    pub rng: GameRng,
+    pub score: u64,
+}
+
+/// 撤销 / 重做栈
+#[derive(Clone, Default)]
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// 每局可撤销的次数，`None` 表示不限
+    budget: Option<usize>,
+    used: usize,
+}
+
+impl History {
+    pub fn new(budget: Option<usize>) -> Self {
+        Self {
+            budget,
+            ..Default::default()
+        }
+    }
+
+    /// 从存档恢复
+    pub fn from_parts(
+        budget: Option<usize>,
+        used: usize,
+        undo: Vec<Snapshot>,
+        redo: Vec<Snapshot>,
+    ) -> Self {
+        let mut history = Self {
+            undo,
+            redo,
+            budget,
+            used,
+        };
+        history.trim();
+        history
+    }
+
+    /// 在执行一步之前记录局面，新的一步会清空重做栈
+    pub fn record(&mut self, snapshot: Snapshot) {
+        self.undo.push(snapshot);
+        self.redo.clear();
+        self.trim();
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() && self.remaining().is_none_or(|x| x > 0)
+    }
+
+    pub fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        if !self.can_undo() {
+            return None;
+        }
+        let snapshot = self.undo.pop()?;
+        self.redo.push(current);
+        self.used += 1;
+        self.trim();
+        Some(snapshot)
+    }
+
+    pub fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let snapshot = self.redo.pop()?;
+        self.undo.push(current);
+        self.trim();
+        Some(snapshot)
+    }
+
+    /// 可以撤销回去的局面，最早的在前
+    pub fn undo_stack(&self) -> &[Snapshot] {
+        &self.undo
+    }
+
+    /// 可以重做的局面，最后一次撤销的在末尾
+    pub fn redo_stack(&self) -> &[Snapshot] {
+        &self.redo
+    }
+
+    /// 本局已经撤销过的次数
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    pub fn remaining(&self) -> Option<usize> {
+        self.budget.map(|x| x.saturating_sub(self.used))
+    }
+
+    /// 撤销次数用不完的局面没有必要保留，只留下最近的几步
+    fn trim(&mut self) {
+        if let Some(remaining) = self.remaining() {
+            let excess = self.undo.len().saturating_sub(remaining);
+            self.undo.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn snapshot(score: u64) -> Snapshot {
+        Snapshot {
+            cells: Grid::new(),
+            rng: GameRng::seed_from_u64(0),
+            score,
+        }
+    }
+
+    #[test]
+    fn keeps_only_undoable_snapshots() {
+        let mut history = History::new(Some(2));
+        for score in 0..10 {
+            history.record(snapshot(score));
+        }
+        let scores = history
+            .undo_stack()
+            .iter()
+            .map(|x| x.score)
+            .collect::<Vec<_>>();
+        assert_eq!(scores, [8, 9]);
+
+        assert_eq!(history.undo(snapshot(10)).unwrap().score, 9);
+        assert_eq!(history.undo_stack().len(), 1);
+        assert_eq!(history.redo(snapshot(9)).unwrap().score, 10);
+        assert_eq!(history.undo_stack().len(), 1);
+        assert_eq!(history.undo(snapshot(10)).unwrap().score, 9);
+        assert!(!history.can_undo());
+        assert!(history.undo_stack().is_empty());
+    }
+
+    #[test]
+    fn no_snapshots_without_budget() {
+        let mut history = History::new(Some(0));
+        history.record(snapshot(0));
+        assert!(history.undo_stack().is_empty());
+
+        let mut history = History::new(None);
+        for score in 0..10 {
+            history.record(snapshot(score));
+        }
+        assert_eq!(history.undo_stack().len(), 10);
+    }
+}
@@ -1,46 +1,271 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use rand::{Rng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use spawn::{SpawnPolicy, StandardSpawn};
 
-use crate::app::structs::{AnimationCell, Cell, CellAnimationType, Vec2};
+use crate::app::structs::{AnimationCell, Cell, CellAnimationType, CellMotionDirection, Vec2};
 
+pub mod ai;
+pub mod bitboard;
+pub mod bot;
 pub mod colors;
 pub mod fx;
+pub mod game;
+pub mod history;
 pub mod movement;
+pub mod puzzle;
+pub mod replay;
+pub mod save;
+pub mod spawn;
 
 pub type Grid = Vec<Vec<Cell>>;
 
-pub fn start_up(cells: &mut Grid) -> AnimationCell {
-    let mut rng = rand::thread_rng();
-    let x = rng.gen_range(0..4_usize);
-    let y = rng.gen_range(0..4_usize);
+/// 每局游戏使用的随机数生成器，同一个种子总是得到同样的对局
+pub type GameRng = ChaCha8Rng;
 
-    cells[x][y] = Cell::new(2);
-    AnimationCell {
-        src: Vec2 { x, y },
-        animation_type: CellAnimationType::Popup,
-        dest: None,
-        value: 2,
-        duration: Duration::default(),
+/// 菜单中可供选择的棋盘大小
+pub const BOARD_SIZES: [usize; 5] = [3, 4, 5, 6, 8];
+
+pub const DEFAULT_BOARD_SIZE: usize = 4;
+
+/// 录像、存档和谜题文件里允许的最大棋盘边长，防止损坏的文件占用过多内存
+pub const MAX_BOARD_SIZE: usize = 16;
+
+/// 菜单中可供选择的胜利目标
+pub const TARGET_TILES: [u64; 5] = [512, 1024, 2048, 4096, 8192];
+
+pub const DEFAULT_TARGET_TILE: u64 = 2048;
+
+/// 限时模式每局的时间
+pub const TIME_ATTACK_LIMIT: Duration = Duration::from_secs(180);
+
+/// 限时模式最后这段时间里会有警示效果
+pub const TIME_ATTACK_WARNING: Duration = Duration::from_secs(10);
+
+/// 限步模式每局可用的有效移动次数
+pub const MOVE_LIMIT: u32 = 200;
+
+/// 游戏模式，玩家记录中按编号保存
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    /// 在限定时间内争取最高分
+    TimeAttack,
+    /// 在限定的移动次数内争取最高分
+    LimitedMoves,
+    /// 所有玩家每天使用同一个种子，每人只有一次机会
+    Daily,
+    /// 从给定的局面出发完成谜题的目标
+    Puzzle,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 5] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::LimitedMoves,
+        GameMode::Daily,
+        GameMode::Puzzle,
+    ];
+
+    /// 有排行榜的模式，谜题只记录是否完成
+    pub const RANKED: [GameMode; 4] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::LimitedMoves,
+        GameMode::Daily,
+    ];
+
+    /// 菜单里可以自由选择的模式，每日挑战有单独的入口
+    pub const FREE_PLAY: [GameMode; 3] = [
+        GameMode::Classic,
+        GameMode::TimeAttack,
+        GameMode::LimitedMoves,
+    ];
+
+    pub fn id(self) -> i32 {
+        match self {
+            Self::Classic => 0,
+            Self::TimeAttack => 1,
+            Self::LimitedMoves => 2,
+            Self::Daily => 3,
+            Self::Puzzle => 4,
+        }
+    }
+
+    pub fn from_id(id: i32) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.id() == id)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "经典",
+            Self::TimeAttack => "限时",
+            Self::LimitedMoves => "限步",
+            Self::Daily => "每日挑战",
+            Self::Puzzle => "谜题",
+        }
+    }
+
+    /// 是否以合成目标地块判定胜利，其余模式只比分数
+    pub fn has_target(self) -> bool {
+        matches!(self, Self::Classic)
+    }
+
+    /// 每局可用的游玩时间，`None` 表示不限
+    pub fn time_limit(self) -> Option<Duration> {
+        match self {
+            Self::TimeAttack => Some(TIME_ATTACK_LIMIT),
+            _ => None,
+        }
+    }
+
+    /// 每局可用的有效移动次数，`None` 表示不限
+    pub fn move_limit(self) -> Option<u32> {
+        match self {
+            Self::LimitedMoves => Some(MOVE_LIMIT),
+            _ => None,
+        }
+    }
+
+    /// 在 `modes` 中循环切换
+    pub fn cycle(self, modes: &[GameMode], forward: bool) -> Self {
+        let len = modes.len();
+        let index = modes.iter().position(|x| *x == self).unwrap_or_default();
+        let index = if forward {
+            (index + 1) % len
+        } else {
+            (index + len - 1) % len
+        };
+        modes[index]
+    }
+}
+
+/// 开局规则
+#[derive(Clone)]
+pub struct GameConfig {
+    pub board_size: usize,
+    /// 开局时放置的地块数量
+    pub opening_tiles: usize,
+    pub spawn: Arc<dyn SpawnPolicy>,
+    /// 每局可撤销的次数，`None` 表示不限
+    pub undo_budget: Option<usize>,
+    /// 合成该地块即获胜，为 0 时不判定胜利
+    pub target_tile: u64,
+    pub mode: GameMode,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            board_size: DEFAULT_BOARD_SIZE,
+            opening_tiles: 2,
+            spawn: Arc::new(StandardSpawn::default()),
+            undo_budget: Some(3),
+            target_tile: DEFAULT_TARGET_TILE,
+            mode: GameMode::default(),
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn with_board_size(board_size: usize) -> Self {
+        Self {
+            board_size,
+            ..Default::default()
+        }
+    }
+
+    /// 每日挑战的规则，大家的条件完全相同，也不能撤销
+    pub fn daily() -> Self {
+        Self {
+            undo_budget: Some(0),
+            target_tile: 0,
+            mode: GameMode::Daily,
+            ..Default::default()
+        }
+    }
+}
+
+/// 某一天的每日挑战种子，`day` 是 `utils::day_of` 得到的天数
+///
+/// 用 SplitMix64 打散，不依赖 `rand` 的实现，换了版本也保持不变
+pub fn daily_seed(day: i64) -> u64 {
+    split_mix(
+        (day as u64)
+            .wrapping_add(0x2048)
+            .wrapping_mul(0x9E3779B97F4A7C15),
+    )
+}
+
+/// 由本局的种子派生出的第 `n` 个种子，同一局里得到的总是同一串种子
+pub fn derive_seed(seed: u64, n: u64) -> u64 {
+    split_mix(seed.wrapping_add(n.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15)))
+}
+
+fn split_mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+pub fn new_grid(size: usize) -> Grid {
+    vec![vec![Cell::default(); size]; size]
+}
+
+pub fn random_seed() -> u64 {
+    rand::thread_rng().r#gen()
+}
+
+pub fn format_seed(seed: u64) -> String {
+    format!("{seed:016X}")
+}
+
+/// 解析十六进制种子，允许带 `0x` 前缀
+pub fn parse_seed(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let text = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    if text.is_empty() {
+        return None;
     }
+    u64::from_str_radix(text, 16).ok()
 }
 
-#[allow(clippy::needless_range_loop)]
-pub fn add_cell(cells: &mut Grid) -> Option<AnimationCell> {
+pub fn start_up<R: Rng>(cells: &mut Grid, config: &GameConfig, rng: &mut R) -> Vec<AnimationCell> {
+    (0..config.opening_tiles.max(1))
+        .filter_map(|_| add_cell(cells, config.spawn.as_ref(), rng))
+        .collect()
+}
+
+pub fn add_cell<R: Rng>(
+    cells: &mut Grid,
+    spawn: &dyn SpawnPolicy,
+    rng: &mut R,
+) -> Option<AnimationCell> {
     let mut empty_cells = Vec::new();
-    for i in 0..4 {
-        for j in 0..4 {
-            if cells[i][j].empty() {
+    for (i, row) in cells.iter().enumerate() {
+        for (j, cell) in row.iter().enumerate() {
+            if cell.empty() {
                 empty_cells.push(Vec2 { x: i, y: j });
             }
         }
     }
 
-    let mut rng = rand::thread_rng();
-    empty_cells.shuffle(&mut rng);
+    empty_cells.shuffle(rng);
     if !empty_cells.is_empty() {
         let coord = empty_cells[0];
-        cells[coord.x][coord.y] = Cell::new(2);
+        let exponent = spawn.spawn_exponent(rng);
+        // 指数为 0 表示这一步不生成地块
+        if exponent == 0 {
+            return None;
+        }
+        let cell = Cell::new(exponent);
+        cells[coord.x][coord.y] = cell;
         return Some(AnimationCell {
             src: Vec2 {
                 x: coord.x,
@@ -48,40 +273,87 @@ pub fn add_cell(cells: &mut Grid) -> Option<AnimationCell> {
             },
             animation_type: CellAnimationType::Popup,
             dest: None,
-            value: 2,
+            cell,
             duration: Duration::default(),
         });
     }
     None
 }
 
+/// 向指定方向移动，地块改变过时再生成一个新地块
+pub fn apply_move<R: Rng>(
+    cells: &mut Grid,
+    direction: CellMotionDirection,
+    spawn: &dyn SpawnPolicy,
+    rng: &mut R,
+) -> (Vec<AnimationCell>, u64) {
+    let (mut animations, score) = movement::swipe(cells, direction);
+    if !animations.is_empty() {
+        add_cell(cells, spawn, rng)
+            .iter()
+            .for_each(|x| animations.push(*x));
+    }
+    animations.sort_by(|a, b| a.animation_type.partial_cmp(&b.animation_type).unwrap());
+    (animations, score)
+}
+
+/// 棋盘上最大的地块，空棋盘为空格
+pub fn max_tile(cells: &Grid) -> Cell {
+    cells
+        .iter()
+        .flatten()
+        .max_by_key(|x| x.exponent())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// 玩家记录中的数值只有 32 位，超出时取最大值
+pub fn to_record(value: u64) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
 pub fn check_game_over(cells: &mut Grid) -> bool {
     if cells.iter().any(|x| x.iter().any(|y| y.empty())) {
         return false;
     }
 
-    let mut game_over = true;
-
-    for i in 0..3 {
-        for j in 0..3 {
-            if cells[i][j] == cells[i][j + 1] {
-                game_over = false;
+    let size = cells.len();
+    for i in 0..size {
+        for j in 0..size {
+            if j + 1 < size && cells[i][j] == cells[i][j + 1] {
+                return false;
             }
-            if cells[i][j] == cells[i + 1][j] {
-                game_over = false;
+            if i + 1 < size && cells[i][j] == cells[i + 1][j] {
+                return false;
             }
         }
     }
-    for i in 1..4 {
-        for j in 1..4 {
-            if cells[i][j] == cells[i][j - 1] {
-                game_over = false;
-            }
-            if cells[i][j] == cells[i - 1][j] {
-                game_over = false;
-            }
-        }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_seed_is_stable() {
+        // 换了实现也不能改变已有的每日挑战
+        assert_eq!(daily_seed(0), 0x53D0F786816FA871);
+        assert_eq!(daily_seed(20000), 0xBECFBBFF08055866);
     }
 
-    game_over
+    #[test]
+    fn derived_seeds_are_distinct() {
+        let seeds = (0..100).map(|x| derive_seed(42, x)).collect::<Vec<_>>();
+        assert_eq!(
+            seeds,
+            (0..100).map(|x| derive_seed(42, x)).collect::<Vec<_>>()
+        );
+        let mut unique = seeds.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), seeds.len());
+        assert_ne!(derive_seed(42, 0), derive_seed(43, 0));
+    }
 }
@@ -2,12 +2,45 @@ use std::time::Duration;
 
 use crate::app::structs::{AnimationCell, Cell, CellAnimationType, CellMotionDirection, Vec2};
 
-use super::Grid;
+use super::{Grid, bitboard::Bitboard};
+
+/// 向指定方向移动，4×4 的棋盘用位棋盘计算，其他情况逐行移动
+pub fn swipe(cells: &mut Grid, direction: CellMotionDirection) -> (Vec<AnimationCell>, u64) {
+    if let Some(board) = Bitboard::from_grid(cells)
+        && let Some((board, animations, score)) = board.swipe_animated(direction)
+    {
+        board.write_grid(cells);
+        return (animations, score);
+    }
+    match direction {
+        CellMotionDirection::Up => move_up(cells),
+        CellMotionDirection::Down => move_down(cells),
+        CellMotionDirection::Left => move_left(cells),
+        CellMotionDirection::Right => move_right(cells),
+    }
+}
+
+/// 把第 `index` 条线上的第 `k` 个位置换算回棋盘坐标，`k = 0` 是移动方向的尽头
+pub fn line_coord(direction: CellMotionDirection, index: usize, size: usize, k: usize) -> Vec2 {
+    let last = size - 1;
+    match direction {
+        CellMotionDirection::Up => Vec2 { x: k, y: index },
+        CellMotionDirection::Down => Vec2 {
+            x: last - k,
+            y: index,
+        },
+        CellMotionDirection::Left => Vec2 { x: index, y: k },
+        CellMotionDirection::Right => Vec2 {
+            x: index,
+            y: last - k,
+        },
+    }
+}
 
-pub fn move_up(cells: &mut Grid) -> (Vec<AnimationCell>, u16) {
+pub fn move_up(cells: &mut Grid) -> (Vec<AnimationCell>, u64) {
     let mut animations = Vec::new();
-    let mut total_score = 0;
-    for i in 0..4 {
+    let mut total_score = 0_u64;
+    for i in 0..cells.len() {
         let mut tmp = Vec::new();
         for line in cells.iter_mut() {
             let ptr = line.as_mut_ptr();
@@ -15,47 +48,47 @@ pub fn move_up(cells: &mut Grid) -> (Vec<AnimationCell>, u16) {
         }
         let (anims, score) = swipe_line(&mut tmp, CellMotionDirection::Up, i);
         animations.extend(anims);
-        total_score += score;
+        total_score = total_score.saturating_add(score);
     }
     (animations, total_score)
 }
 
-pub fn move_down(cells: &mut Grid) -> (Vec<AnimationCell>, u16) {
+pub fn move_down(cells: &mut Grid) -> (Vec<AnimationCell>, u64) {
     let mut animations = Vec::new();
-    let mut total_score = 0;
-    for i in 0..4 {
+    let mut total_score = 0_u64;
+    for i in 0..cells.len() {
         let mut tmp = Vec::new();
-        for j in (0..4).rev() {
+        for j in (0..cells.len()).rev() {
             let ptr = cells[j].as_mut_ptr();
             tmp.push(unsafe { &mut *ptr.add(i) });
         }
         let (anims, score) = swipe_line(&mut tmp, CellMotionDirection::Down, i);
         animations.extend(anims);
-        total_score += score;
+        total_score = total_score.saturating_add(score);
     }
     (animations, total_score)
 }
 
-pub fn move_left(cells: &mut Grid) -> (Vec<AnimationCell>, u16) {
+pub fn move_left(cells: &mut Grid) -> (Vec<AnimationCell>, u64) {
     let mut animations = Vec::new();
-    let mut total_score = 0;
+    let mut total_score = 0_u64;
     for (i, row) in cells.iter_mut().enumerate() {
         let mut tmp = row.iter_mut().collect::<Vec<_>>();
         let (anims, score) = swipe_line(&mut tmp, CellMotionDirection::Left, i);
         animations.extend(anims);
-        total_score += score;
+        total_score = total_score.saturating_add(score);
     }
     (animations, total_score)
 }
 
-pub fn move_right(cells: &mut Grid) -> (Vec<AnimationCell>, u16) {
+pub fn move_right(cells: &mut Grid) -> (Vec<AnimationCell>, u64) {
     let mut animations = Vec::new();
-    let mut total_score = 0;
+    let mut total_score = 0_u64;
     for (i, row) in cells.iter_mut().enumerate() {
         let mut tmp = row.iter_mut().rev().collect::<Vec<_>>();
         let (anims, score) = swipe_line(&mut tmp, CellMotionDirection::Right, i);
         animations.extend(anims);
-        total_score += score;
+        total_score = total_score.saturating_add(score);
     }
     (animations, total_score)
 }
@@ -64,11 +97,14 @@ fn swipe_line(
     cells: &mut [&mut Cell],
     direction: CellMotionDirection,
     index: usize,
-) -> (Vec<AnimationCell>, u16) {
+) -> (Vec<AnimationCell>, u64) {
     let mut animations = Vec::new();
     let mut score = 0;
 
-    for i in 1..4 {
+    let size = cells.len();
+    let coord = |k: usize| line_coord(direction, index, size, k);
+
+    for i in 1..cells.len() {
         if cells[i].empty() {
             continue;
         }
@@ -78,34 +114,15 @@ fn swipe_line(
             temp -= 1;
         }
 
-        let (temp_coord, i_coord) = match &direction {
-            CellMotionDirection::Up => (Vec2 { x: temp, y: index }, Vec2 { x: i, y: index }),
-            CellMotionDirection::Down => (
-                Vec2 {
-                    x: 3 - temp,
-                    y: index,
-                },
-                Vec2 { x: 3 - i, y: index },
-            ),
-            CellMotionDirection::Left => (Vec2 { x: index, y: temp }, Vec2 { x: index, y: i }),
-            CellMotionDirection::Right => (
-                Vec2 {
-                    x: index,
-                    y: 3 - temp,
-                },
-                Vec2 { x: index, y: 3 - i },
-            ),
-        };
-
         if cells[temp] == cells[i] {
-            cells[temp].set(*cells[temp] * 2_u16);
-            score = cells[temp].get();
+            cells[temp].set(cells[temp].merged());
+            score = cells[temp].value();
 
             animations.push(AnimationCell {
-                src: i_coord,
-                value: cells[i].get(),
+                src: coord(i),
+                cell: *cells[i],
                 animation_type: CellAnimationType::Move,
-                dest: Some(temp_coord),
+                dest: Some(coord(temp)),
                 duration: Duration::default(),
             });
 
@@ -114,10 +131,10 @@ fn swipe_line(
             cells[temp].set(*cells[i]);
 
             animations.push(AnimationCell {
-                src: i_coord,
-                value: cells[i].get(),
+                src: coord(i),
+                cell: *cells[i],
                 animation_type: CellAnimationType::Move,
-                dest: Some(temp_coord),
+                dest: Some(coord(temp)),
                 duration: Duration::default(),
             });
 
@@ -126,23 +143,11 @@ fn swipe_line(
             temp += 1;
             cells[temp].set(*cells[i]);
 
-            let temp_coord = match &direction {
-                CellMotionDirection::Up => Vec2 { x: temp, y: index },
-                CellMotionDirection::Down => Vec2 {
-                    x: 3 - temp,
-                    y: index,
-                },
-                CellMotionDirection::Left => Vec2 { x: index, y: temp },
-                CellMotionDirection::Right => Vec2 {
-                    x: index,
-                    y: 3 - temp + 1,
-                },
-            };
             animations.push(AnimationCell {
-                src: i_coord,
-                value: cells[i].get(),
+                src: coord(i),
+                cell: *cells[i],
                 animation_type: CellAnimationType::Move,
-                dest: Some(temp_coord),
+                dest: Some(coord(temp)),
                 duration: Duration::default(),
             });
 
@@ -0,0 +1,387 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::app::{
+    structs::{Cell, Player},
+    utils::data_dir,
+};
+
+use super::{
+    GameConfig, GameMode, Grid, MAX_BOARD_SIZE, format_seed,
+    game::Game,
+    new_grid, parse_seed,
+    spawn::{self, SpawnPolicy},
+};
+
+/// 谜题文件的文件头
+pub const PUZZLE_MAGIC: &str = "T2048P";
+
+/// 谜题格式版本，格式变化时递增
+pub const PUZZLE_VERSION: u32 = 1;
+
+pub const PUZZLE_EXTENSION: &str = "t2p";
+
+/// 随游戏附带的谜题，按推荐的顺序排列
+const BUNDLED: [(&str, &str); 6] = [
+    ("one-step", include_str!("../puzzles/one-step.t2p")),
+    ("clean-sweep", include_str!("../puzzles/clean-sweep.t2p")),
+    ("chain", include_str!("../puzzles/chain.t2p")),
+    ("quick-512", include_str!("../puzzles/quick-512.t2p")),
+    ("all-in-one", include_str!("../puzzles/all-in-one.t2p")),
+    ("corner", include_str!("../puzzles/corner.t2p")),
+];
+
+/// 谜题的完成条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleGoal {
+    /// 合成不小于该数值的地块
+    Tile(u64),
+    /// 棋盘上只剩下一个地块
+    SingleTile,
+}
+
+impl PuzzleGoal {
+    pub fn text(self) -> String {
+        match self {
+            Self::Tile(tile) => format!("合成 {tile}"),
+            Self::SingleTile => String::from("只剩一块"),
+        }
+    }
+}
+
+/// 从给定局面出发的一道谜题
+///
+/// 文件为纯文本，第一行是文件头和版本号，之后每行一个字段：
+///
+/// ```text
+/// T2048P 1
+/// name 速成 512
+/// goal tile 512
+/// moves 20
+/// spawn standard:0.1
+/// undo 3
+/// seed 0123456789ABCDEF
+/// row 256 128 64 32
+/// row 0 0 16 16
+/// row 0 0 0 0
+/// row 0 0 0 0
+/// ```
+///
+/// `goal` 为 `tile <数值>` 或 `single`，`row` 是按行排列的地块数值，0 表示空格，
+/// 行数即棋盘边长。`moves`、`spawn`、`undo` 和 `seed` 可以省略，分别表示不限步数、
+/// 经典的生成规则、默认的撤销次数和由谜题编号得到的种子。`spawn none` 表示移动后
+/// 不生成新地块
+#[derive(Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub goal: PuzzleGoal,
+    /// 可用的有效移动次数，`None` 表示不限
+    pub move_limit: Option<u32>,
+    pub spawn: Arc<dyn SpawnPolicy>,
+    pub undo_budget: Option<usize>,
+    /// 同一道谜题总是生成同样的地块，玩家记录也靠它认出谜题
+    pub seed: u64,
+    pub cells: Grid,
+    pub bundled: bool,
+}
+
+impl Puzzle {
+    /// `id` 为内置谜题的 `builtin/<名称>` 或玩家谜题的文件名，用作默认的名称和种子
+    pub fn decode(id: &str, text: &str) -> io::Result<Self> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or_else(|| invalid("empty puzzle"))?;
+        let Some((PUZZLE_MAGIC, version)) = header.split_once(' ') else {
+            return Err(invalid("not a puzzle file"));
+        };
+        let version = version
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| invalid("bad puzzle version"))?;
+        if version > PUZZLE_VERSION {
+            return Err(invalid(&format!("unsupported puzzle version {version}")));
+        }
+
+        let defaults = GameConfig::default();
+        let mut name = None;
+        let mut goal = None;
+        let mut move_limit = None;
+        let mut spawn = defaults.spawn;
+        let mut undo_budget = defaults.undo_budget;
+        let mut seed = None;
+        let mut rows = Vec::new();
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+            match key {
+                "name" => name = Some(value.to_string()),
+                "goal" => goal = Some(decode_goal(value)?),
+                "moves" => move_limit = Some(value.parse().map_err(|_| invalid("bad move limit"))?),
+                "spawn" => {
+                    spawn = spawn::from_descriptor(value)
+                        .ok_or_else(|| invalid("unknown spawn policy"))?
+                }
+                "undo" => {
+                    undo_budget = match value {
+                        "-" => None,
+                        _ => Some(value.parse().map_err(|_| invalid("bad undo budget"))?),
+                    }
+                }
+                "seed" => seed = Some(parse_seed(value).ok_or_else(|| invalid("bad seed"))?),
+                "row" => rows.push(decode_row(value)?),
+                // 未来版本新增的字段
+                _ => (),
+            }
+        }
+
+        let size = rows.len();
+        if !(2..=MAX_BOARD_SIZE).contains(&size) || rows.iter().any(|x| x.len() != size) {
+            return Err(invalid("board must be square"));
+        }
+        let mut cells = new_grid(size);
+        for (row, values) in cells.iter_mut().zip(rows) {
+            row.copy_from_slice(&values);
+        }
+        if cells.iter().flatten().all(|x| x.empty()) {
+            return Err(invalid("empty board"));
+        }
+
+        Ok(Self {
+            name: name.unwrap_or_else(|| id.to_string()),
+            goal: goal.ok_or_else(|| invalid("missing goal"))?,
+            move_limit,
+            spawn,
+            undo_budget,
+            seed: seed.unwrap_or_else(|| fnv1a(id.bytes())),
+            cells,
+            bundled: false,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{PUZZLE_MAGIC} {PUZZLE_VERSION}");
+        let _ = writeln!(text, "name {}", self.name);
+        let _ = match self.goal {
+            PuzzleGoal::Tile(tile) => writeln!(text, "goal tile {tile}"),
+            PuzzleGoal::SingleTile => writeln!(text, "goal single"),
+        };
+        if let Some(moves) = self.move_limit {
+            let _ = writeln!(text, "moves {moves}");
+        }
+        let _ = writeln!(text, "spawn {}", self.spawn.descriptor());
+        let _ = writeln!(
+            text,
+            "undo {}",
+            self.undo_budget
+                .map_or(String::from("-"), |x| x.to_string())
+        );
+        let _ = writeln!(text, "seed {}", format_seed(self.seed));
+        for row in &self.cells {
+            let _ = write!(text, "row");
+            for cell in row {
+                let _ = write!(text, " {}", cell.value());
+            }
+            let _ = writeln!(text);
+        }
+        text
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.encode())
+    }
+
+    /// 读取玩家的谜题文件，编号取文件名
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let id = path
+            .file_stem()
+            .map_or(String::new(), |x| x.to_string_lossy().into_owned());
+        Self::decode(&id, &fs::read_to_string(path)?)
+    }
+
+    pub fn board_size(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// 谜题的开局规则，合成目标地块交给游戏本身判定
+    pub fn config(&self) -> GameConfig {
+        GameConfig {
+            board_size: self.board_size(),
+            opening_tiles: 0,
+            spawn: self.spawn.clone(),
+            undo_budget: self.undo_budget,
+            target_tile: match self.goal {
+                PuzzleGoal::Tile(tile) => tile,
+                PuzzleGoal::SingleTile => 0,
+            },
+            mode: GameMode::Puzzle,
+        }
+    }
+
+    /// 从谜题的局面开始的一局
+    pub fn start(&self) -> Game {
+        Game::from_cells(self.config(), self.seed, self.cells.clone())
+    }
+
+    pub fn is_solved(&self, game: &Game) -> bool {
+        match self.goal {
+            PuzzleGoal::Tile(_) => game.has_won(),
+            PuzzleGoal::SingleTile => {
+                game.cells().iter().flatten().filter(|x| !x.empty()).count() == 1
+            }
+        }
+    }
+
+    /// 步数已经用完
+    pub fn out_of_moves(&self, game: &Game) -> bool {
+        self.move_limit
+            .is_some_and(|x| game.stats().total_moves() >= x)
+    }
+
+    pub fn goal_text(&self) -> String {
+        self.goal.text()
+    }
+
+    /// 记录中区分谜题的编号，文件改名或种子相同都不会混淆不同的谜题
+    pub fn id(&self) -> u64 {
+        let name = self.name.bytes();
+        let cells = self.cells.iter().flatten().map(|x| x.exponent());
+        fnv1a(name.chain([b'\n']).chain(cells))
+    }
+
+    /// 玩家是否完成过这道谜题
+    pub fn solved_by(&self, player: &Player) -> bool {
+        let id = self.id();
+        player
+            .records
+            .iter()
+            .any(|x| x.mode() == GameMode::Puzzle && x.puzzle as u64 == id && x.won)
+    }
+}
+
+/// 玩家自己的谜题文件所在的目录
+pub fn puzzle_dir() -> PathBuf {
+    data_dir().join("puzzles")
+}
+
+pub fn bundled() -> Vec<Puzzle> {
+    BUNDLED
+        .iter()
+        .filter_map(|(name, text)| Puzzle::decode(&format!("builtin/{name}"), text).ok())
+        .map(|x| Puzzle { bundled: true, ..x })
+        .collect()
+}
+
+/// 读取内置谜题和玩家的谜题，无法读取的文件连同原因一起返回
+pub fn load_all() -> (Vec<Puzzle>, Vec<(PathBuf, io::Error)>) {
+    let mut puzzles = bundled();
+    let mut errors = Vec::new();
+    let mut paths = match fs::read_dir(puzzle_dir()) {
+        Ok(entries) => entries
+            .filter_map(|x| x.ok())
+            .map(|x| x.path())
+            .filter(|x| x.extension().is_some_and(|x| x == PUZZLE_EXTENSION))
+            .collect::<Vec<_>>(),
+        Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            errors.push((puzzle_dir(), e));
+            Vec::new()
+        }
+    };
+    paths.sort();
+    for path in paths {
+        match Puzzle::load(&path) {
+            Ok(puzzle) => puzzles.push(puzzle),
+            Err(e) => errors.push((path, e)),
+        }
+    }
+    (puzzles, errors)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn decode_goal(text: &str) -> io::Result<PuzzleGoal> {
+    let (kind, value) = text.split_once(' ').unwrap_or((text, ""));
+    match kind {
+        "tile" => {
+            let tile = value
+                .trim()
+                .parse::<u64>()
+                .map_err(|_| invalid("bad goal tile"))?;
+            if tile < 4 || !tile.is_power_of_two() {
+                return Err(invalid("bad goal tile"));
+            }
+            Ok(PuzzleGoal::Tile(tile))
+        }
+        "single" => Ok(PuzzleGoal::SingleTile),
+        _ => Err(invalid("unknown goal")),
+    }
+}
+
+fn decode_row(text: &str) -> io::Result<Vec<Cell>> {
+    text.split_whitespace()
+        .map(|x| {
+            let value = x.parse::<u64>().map_err(|_| invalid("bad cell"))?;
+            match value {
+                0 => Ok(Cell::default()),
+                _ if value >= 2 && value.is_power_of_two() => {
+                    Ok(Cell::new(value.trailing_zeros() as u8))
+                }
+                _ => Err(invalid("bad cell")),
+            }
+        })
+        .collect()
+}
+
+/// 没有写明种子时由编号得到种子，谜题的编号也由此得到，FNV-1a 保证每次结果相同
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xCBF29CE484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::structs::PlayerRecord;
+
+    const TEXT: &str = "T2048P 1\nname 测试\ngoal tile 64\nseed 1\nrow 32 32\nrow 0 2\n";
+
+    #[test]
+    fn id_survives_renaming() {
+        let puzzle = Puzzle::decode("a", TEXT).unwrap();
+        assert_eq!(puzzle.id(), Puzzle::decode("b", TEXT).unwrap().id());
+    }
+
+    #[test]
+    fn same_seed_different_puzzles() {
+        let puzzle = Puzzle::decode("a", TEXT).unwrap();
+        let board = Puzzle::decode("a", &TEXT.replace("row 0 2", "row 2 0")).unwrap();
+        let name = Puzzle::decode("a", &TEXT.replace("测试", "另一道")).unwrap();
+        assert_eq!(puzzle.seed, board.seed);
+        assert_ne!(puzzle.id(), board.id());
+        assert_ne!(puzzle.id(), name.id());
+
+        let mut player = Player::default();
+        player.records.push(PlayerRecord {
+            mode: GameMode::Puzzle.id(),
+            seed: puzzle.seed as i64,
+            puzzle: puzzle.id() as i64,
+            won: true,
+            ..Default::default()
+        });
+        assert!(puzzle.solved_by(&player));
+        assert!(!board.solved_by(&player));
+    }
+}
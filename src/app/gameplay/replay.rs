@@ -0,0 +1,390 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::app::{
+    structs::{AnimationCell, CellMotionDirection},
+    utils::{data_dir, write_atomic},
+};
+
+use super::{GameConfig, GameMode, Grid, MAX_BOARD_SIZE, game::Game, parse_seed, spawn};
+
+/// 录像文件的文件头
+pub const REPLAY_MAGIC: &str = "T2048R";
+
+/// 录像格式版本，格式变化时递增
+pub const REPLAY_VERSION: u32 = 1;
+
+pub const REPLAY_EXTENSION: &str = "t2r";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayAction {
+    Move(CellMotionDirection),
+    Undo,
+    Redo,
+}
+
+impl ReplayAction {
+    fn to_char(self) -> char {
+        match self {
+            Self::Move(CellMotionDirection::Up) => 'U',
+            Self::Move(CellMotionDirection::Down) => 'D',
+            Self::Move(CellMotionDirection::Left) => 'L',
+            Self::Move(CellMotionDirection::Right) => 'R',
+            Self::Undo => 'Z',
+            Self::Redo => 'Y',
+        }
+    }
+
+    fn from_char(c: char) -> Option<Self> {
+        Some(match c {
+            'U' => Self::Move(CellMotionDirection::Up),
+            'D' => Self::Move(CellMotionDirection::Down),
+            'L' => Self::Move(CellMotionDirection::Left),
+            'R' => Self::Move(CellMotionDirection::Right),
+            'Z' => Self::Undo,
+            'Y' => Self::Redo,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayStep {
+    pub action: ReplayAction,
+    /// 距离开始游玩的时间
+    pub time: Duration,
+}
+
+/// 一局游戏的操作记录
+///
+/// 文件为纯文本，第一行是文件头和版本号，之后每行一个字段：
+///
+/// ```text
+/// T2048R 1
+/// seed 0123456789ABCDEF
+/// size 4
+/// opening 2
+/// spawn standard:0.1
+/// score 1024
+/// timestamp 1145141919810
+/// moves U0 L350 Z120 D80
+/// ```
+///
+/// `moves` 中每一项是操作字母加上距离上一项的毫秒数。机器人的录像还有一行
+/// `bot <策略名>`
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    pub seed: u64,
+    pub board_size: usize,
+    pub opening_tiles: usize,
+    pub spawn: String,
+    pub mode: GameMode,
+    pub score: u64,
+    pub timestamp: i64,
+    /// 机器人所用的策略，玩家的录像为 `None`
+    pub bot: Option<String>,
+    pub steps: Vec<ReplayStep>,
+}
+
+impl Replay {
+    pub fn new(config: &GameConfig, seed: u64) -> Self {
+        Self {
+            seed,
+            board_size: config.board_size,
+            opening_tiles: config.opening_tiles,
+            spawn: config.spawn.descriptor(),
+            mode: config.mode,
+            ..Default::default()
+        }
+    }
+
+    pub fn push(&mut self, action: ReplayAction, time: Duration) {
+        self.steps.push(ReplayStep { action, time });
+    }
+
+    /// 还原录制时的开局规则，撤销次数不受限制，也不判定胜利
+    pub fn config(&self) -> io::Result<GameConfig> {
+        let spawn = spawn::from_descriptor(&self.spawn).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown spawn policy: {}", self.spawn),
+            )
+        })?;
+        Ok(GameConfig {
+            board_size: self.board_size,
+            opening_tiles: self.opening_tiles,
+            spawn,
+            undo_budget: None,
+            target_tile: 0,
+            mode: self.mode,
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{REPLAY_MAGIC} {REPLAY_VERSION}");
+        let _ = writeln!(text, "seed {}", super::format_seed(self.seed));
+        let _ = writeln!(text, "size {}", self.board_size);
+        let _ = writeln!(text, "opening {}", self.opening_tiles);
+        let _ = writeln!(text, "spawn {}", self.spawn);
+        let _ = writeln!(text, "mode {}", self.mode.id());
+        let _ = writeln!(text, "score {}", self.score);
+        let _ = writeln!(text, "timestamp {}", self.timestamp);
+        if let Some(ref bot) = self.bot {
+            let _ = writeln!(text, "bot {bot}");
+        }
+        let _ = writeln!(text, "moves{}", self.encode_steps());
+        text
+    }
+
+    /// 操作序列，每一项前面带一个空格
+    pub fn encode_steps(&self) -> String {
+        let mut text = String::new();
+        let mut last = Duration::ZERO;
+        for step in &self.steps {
+            let delta = step.time.saturating_sub(last).as_millis();
+            let _ = write!(text, " {}{}", step.action.to_char(), delta);
+            last = step.time;
+        }
+        text
+    }
+
+    /// 把 [`Replay::encode_steps`] 的结果追加到操作序列中
+    pub fn decode_steps(&mut self, text: &str) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+        let mut time = self.steps.last().map_or(Duration::ZERO, |x| x.time);
+        for item in text.split_whitespace() {
+            let mut chars = item.chars();
+            let action = chars
+                .next()
+                .and_then(ReplayAction::from_char)
+                .ok_or_else(|| invalid("bad move"))?;
+            let delta = chars
+                .as_str()
+                .parse::<u64>()
+                .map_err(|_| invalid("bad move time"))?;
+            time = time
+                .checked_add(Duration::from_millis(delta))
+                .ok_or_else(|| invalid("bad move time"))?;
+            self.push(action, time);
+        }
+        Ok(())
+    }
+
+    pub fn decode(text: &str) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let mut lines = text.lines();
+        let header = lines.next().ok_or_else(|| invalid("empty replay"))?;
+        let Some((REPLAY_MAGIC, version)) = header.split_once(' ') else {
+            return Err(invalid("not a replay file"));
+        };
+        let version = version
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| invalid("bad replay version"))?;
+        if version > REPLAY_VERSION {
+            return Err(invalid(&format!("unsupported replay version {version}")));
+        }
+
+        let mut replay = Self::default();
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "seed" => replay.seed = parse_seed(value).ok_or_else(|| invalid("bad seed"))?,
+                "size" => replay.board_size = value.parse().map_err(|_| invalid("bad size"))?,
+                "opening" => {
+                    replay.opening_tiles = value.parse().map_err(|_| invalid("bad opening"))?
+                }
+                "spawn" => replay.spawn = value.to_string(),
+                // 与 `PlayerRecord::mode` 一致，新版本加入的模式按经典模式处理
+                "mode" => {
+                    let id = value.parse().map_err(|_| invalid("bad mode"))?;
+                    replay.mode = GameMode::from_id(id).unwrap_or_default();
+                }
+                "bot" => replay.bot = Some(value.to_string()),
+                "score" => replay.score = value.parse().map_err(|_| invalid("bad score"))?,
+                "timestamp" => {
+                    replay.timestamp = value.parse().map_err(|_| invalid("bad timestamp"))?
+                }
+                "moves" => replay.decode_steps(value)?,
+                // 未来版本新增的字段
+                _ => (),
+            }
+        }
+        if !(2..=MAX_BOARD_SIZE).contains(&replay.board_size) {
+            return Err(invalid("bad size"));
+        }
+        // 开局要逐个放下这些地块，不能超过棋盘的格数
+        if replay.opening_tiles > replay.board_size * replay.board_size {
+            return Err(invalid("bad opening"));
+        }
+        Ok(replay)
+    }
+
+    /// 写到一半崩溃时不会留下截断的录像
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_atomic(path, &self.encode())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::decode(&fs::read_to_string(path)?)
+    }
+}
+
+/// 玩家某条记录对应的录像文件
+pub fn replay_path(player_id: i32, timestamp: i64) -> PathBuf {
+    data_dir()
+        .join("replays")
+        .join(format!("{player_id}_{timestamp}.{REPLAY_EXTENSION}"))
+}
+
+/// 机器人的录像，与玩家的录像分开存放
+pub fn bot_replay_path(timestamp: i64) -> PathBuf {
+    data_dir()
+        .join("replays")
+        .join("bot")
+        .join(format!("{timestamp}.{REPLAY_EXTENSION}"))
+}
+
+/// 用游戏规则重新执行录像里的操作
+pub struct ReplayPlayer {
+    replay: Replay,
+    config: GameConfig,
+    game: Game,
+    position: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> io::Result<Self> {
+        let config = replay.config()?;
+        Ok(Self {
+            game: Game::new(config.clone(), replay.seed),
+            replay,
+            config,
+            position: 0,
+        })
+    }
+
+    /// 回到开局，返回开局地块的动画
+    pub fn reset(&mut self) -> Vec<AnimationCell> {
+        self.game = Game::new(self.config.clone(), self.replay.seed);
+        self.position = 0;
+        self.game.popup_animations()
+    }
+
+    /// 执行下一步，录像结束时返回 `None`
+    pub fn step(&mut self) -> Option<Vec<AnimationCell>> {
+        let step = *self.replay.steps.get(self.position)?;
+        self.position += 1;
+        let animations = match step.action {
+            ReplayAction::Move(direction) => self.game.apply(direction).animations,
+            ReplayAction::Undo => {
+                self.game.undo();
+                Vec::new()
+            }
+            ReplayAction::Redo => {
+                self.game.redo();
+                Vec::new()
+            }
+        };
+        Some(animations)
+    }
+
+    /// 跳到第 `position` 步之后的局面
+    pub fn seek(&mut self, position: usize) {
+        self.reset();
+        while self.position < position.min(self.len()) {
+            self.step();
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    pub fn cells(&self) -> &Grid {
+        self.game.cells()
+    }
+
+    pub fn score(&self) -> u64 {
+        self.game.score()
+    }
+
+    /// 已经执行的步数
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.replay.steps.len()
+    }
+
+    /// 重新执行整局后的分数是否与记录一致
+    pub fn verify(&mut self) -> bool {
+        self.seek(self.len());
+        self.score() == self.replay.score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut replay = Replay::new(&GameConfig::default(), 0x1234);
+        replay.push(ReplayAction::Move(CellMotionDirection::Up), Duration::ZERO);
+        replay.push(ReplayAction::Undo, Duration::from_millis(350));
+        replay.push(ReplayAction::Redo, Duration::from_millis(400));
+        replay.score = 4;
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.encode(), replay.encode());
+    }
+
+    #[test]
+    fn unknown_mode_is_classic() {
+        let mut replay = Replay::new(&GameConfig::daily(), 0);
+        let text = replay.encode().replace("mode 3", "mode 99");
+        assert_eq!(Replay::decode(&text).unwrap().mode, GameMode::Classic);
+        replay.mode = GameMode::Puzzle;
+        assert_eq!(
+            Replay::decode(&replay.encode()).unwrap().mode,
+            GameMode::Puzzle
+        );
+        assert!(Replay::decode(&text.replace("mode 99", "mode x")).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_board() {
+        let mut replay = Replay::new(&GameConfig::default(), 0);
+        for size in [0, 1, MAX_BOARD_SIZE + 1, 100000] {
+            replay.board_size = size;
+            assert!(Replay::decode(&replay.encode()).is_err(), "{size}");
+        }
+    }
+
+    #[test]
+    fn rejects_oversized_opening() {
+        let mut replay = Replay::new(&GameConfig::default(), 0);
+        replay.opening_tiles = 16;
+        assert!(Replay::decode(&replay.encode()).is_ok());
+        for opening in [17, usize::MAX] {
+            replay.opening_tiles = opening;
+            assert!(Replay::decode(&replay.encode()).is_err(), "{opening}");
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_move_times() {
+        let mut replay = Replay::new(&GameConfig::default(), 0);
+        replay.push(ReplayAction::Undo, Duration::MAX);
+        let text = format!("U{}", u64::MAX);
+        assert!(replay.decode_steps(&text).is_err());
+    }
+}
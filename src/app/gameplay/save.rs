@@ -0,0 +1,268 @@
+use std::{
+    fmt::Write as _,
+    io::{self, ErrorKind},
+    time::Duration,
+};
+
+use rand::SeedableRng;
+
+use crate::app::structs::Cell;
+
+use super::{
+    GameConfig, GameMode, GameRng, Grid, MAX_BOARD_SIZE, format_seed,
+    game::{Game, GameStats},
+    history::{History, Snapshot},
+    new_grid, parse_seed,
+    replay::Replay,
+    spawn,
+};
+
+/// 存档的文件头
+pub const SAVE_MAGIC: &str = "T2048S";
+
+/// 存档格式版本，格式变化时递增
+pub const SAVE_VERSION: u32 = 1;
+
+/// 中途退出的一局，下次可以从菜单继续
+///
+/// 与录像一样是纯文本，由数据层按玩家保存：
+///
+/// ```text
+/// T2048S 1
+/// seed 0123456789ABCDEF
+/// size 4
+/// opening 2
+/// spawn standard:0.1
+/// undo 3
+/// target 2048
+/// mode 0
+/// won 0
+/// time 35210
+/// stats 12 8 20 9 31
+/// used 1
+/// state 1024 <rng> 1 0 0 2 ...
+/// past 1020 <rng> 1 0 2 0 ...
+/// future 1032 <rng> 0 0 1 2 ...
+/// moves U0 L350 Z120 D80
+/// ```
+///
+/// `state` 是当前局面，`past` 和 `future` 分别是撤销栈和重做栈，每行一个局面：
+/// 分数、随机数生成器的状态，再加上按行排列的地块指数。`<rng>` 是十六进制的
+/// 种子、流编号和位置。`undo` 为 `-` 表示不限撤销次数，`time` 是游玩的毫秒数，
+/// `stats` 依次是向上、下、左、右移动的次数和合并的次数
+pub struct SavedGame {
+    pub game: Game,
+    pub play_time: Duration,
+    /// 到目前为止的录像，继续游玩后接着录制
+    pub replay: Replay,
+}
+
+impl SavedGame {
+    pub fn encode(&self) -> String {
+        let config = self.game.config();
+        let history = self.game.history();
+        let mut text = String::new();
+        let _ = writeln!(text, "{SAVE_MAGIC} {SAVE_VERSION}");
+        let _ = writeln!(text, "seed {}", format_seed(self.game.seed()));
+        let _ = writeln!(text, "size {}", config.board_size);
+        let _ = writeln!(text, "opening {}", config.opening_tiles);
+        let _ = writeln!(text, "spawn {}", config.spawn.descriptor());
+        let _ = writeln!(
+            text,
+            "undo {}",
+            config
+                .undo_budget
+                .map_or(String::from("-"), |x| x.to_string())
+        );
+        let _ = writeln!(text, "target {}", config.target_tile);
+        let _ = writeln!(text, "mode {}", config.mode.id());
+        let _ = writeln!(text, "won {}", self.game.has_won() as u8);
+        let _ = writeln!(text, "time {}", self.play_time.as_millis());
+        let stats = self.game.stats();
+        let _ = writeln!(
+            text,
+            "stats {} {} {} {} {}",
+            stats.moves[0], stats.moves[1], stats.moves[2], stats.moves[3], stats.merges
+        );
+        let _ = writeln!(text, "used {}", history.used());
+        let _ = writeln!(text, "state {}", encode_snapshot(&self.game.snapshot()));
+        for snapshot in history.undo_stack() {
+            let _ = writeln!(text, "past {}", encode_snapshot(snapshot));
+        }
+        for snapshot in history.redo_stack() {
+            let _ = writeln!(text, "future {}", encode_snapshot(snapshot));
+        }
+        let _ = writeln!(text, "moves{}", self.replay.encode_steps());
+        text
+    }
+
+    pub fn decode(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines();
+        let header = lines.next().ok_or_else(|| invalid("empty save"))?;
+        let Some((SAVE_MAGIC, version)) = header.split_once(' ') else {
+            return Err(invalid("not a save file"));
+        };
+        let version = version
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| invalid("bad save version"))?;
+        if version > SAVE_VERSION {
+            return Err(invalid(&format!("unsupported save version {version}")));
+        }
+
+        let mut seed = None;
+        let mut config = GameConfig::default();
+        let mut won = false;
+        let mut play_time = Duration::ZERO;
+        let mut stats = GameStats::default();
+        let mut used = 0;
+        // 局面要等棋盘大小确定后才能解析
+        let mut state = None;
+        let mut past = Vec::new();
+        let mut future = Vec::new();
+        let mut moves = String::new();
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "seed" => seed = Some(parse_seed(value).ok_or_else(|| invalid("bad seed"))?),
+                "size" => config.board_size = value.parse().map_err(|_| invalid("bad size"))?,
+                "opening" => {
+                    config.opening_tiles = value.parse().map_err(|_| invalid("bad opening"))?
+                }
+                "spawn" => {
+                    config.spawn = spawn::from_descriptor(value)
+                        .ok_or_else(|| invalid("unknown spawn policy"))?
+                }
+                "undo" => {
+                    config.undo_budget = match value {
+                        "-" => None,
+                        _ => Some(value.parse().map_err(|_| invalid("bad undo budget"))?),
+                    }
+                }
+                "target" => {
+                    config.target_tile = value.parse().map_err(|_| invalid("bad target"))?
+                }
+                "mode" => {
+                    config.mode = value
+                        .parse()
+                        .ok()
+                        .and_then(GameMode::from_id)
+                        .ok_or_else(|| invalid("unknown mode"))?
+                }
+                "won" => won = value == "1",
+                "time" => {
+                    play_time =
+                        Duration::from_millis(value.parse().map_err(|_| invalid("bad time"))?)
+                }
+                "stats" => stats = decode_stats(value)?,
+                "used" => used = value.parse().map_err(|_| invalid("bad undo count"))?,
+                "state" => state = Some(value),
+                "past" => past.push(value),
+                "future" => future.push(value),
+                "moves" => moves = value.to_string(),
+                // 未来版本新增的字段
+                _ => (),
+            }
+        }
+
+        let size = config.board_size;
+        if !(2..=MAX_BOARD_SIZE).contains(&size) {
+            return Err(invalid("bad size"));
+        }
+        // 开局要逐个放下这些地块，不能超过棋盘的格数
+        if config.opening_tiles > size * size {
+            return Err(invalid("bad opening"));
+        }
+        let seed = seed.ok_or_else(|| invalid("missing seed"))?;
+        let state = decode_snapshot(state.ok_or_else(|| invalid("missing state"))?, size)?;
+        let parse_all = |values: Vec<&str>| {
+            values
+                .into_iter()
+                .map(|x| decode_snapshot(x, size))
+                .collect::<io::Result<Vec<_>>>()
+        };
+        let history = History::from_parts(
+            config.undo_budget,
+            used,
+            parse_all(past)?,
+            parse_all(future)?,
+        );
+
+        let mut replay = Replay::new(&config, seed);
+        replay.decode_steps(&moves)?;
+        Ok(Self {
+            game: Game::resume(config, seed, state, history, won, stats),
+            play_time,
+            replay,
+        })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn decode_stats(text: &str) -> io::Result<GameStats> {
+    let values = text
+        .split_whitespace()
+        .map(|x| x.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid("bad stats"))?;
+    let [up, down, left, right, merges] = values[..] else {
+        return Err(invalid("bad stats"));
+    };
+    Ok(GameStats {
+        moves: [up, down, left, right],
+        merges,
+    })
+}
+
+fn encode_snapshot(snapshot: &Snapshot) -> String {
+    let rng = &snapshot.rng;
+    let mut text = format!("{} ", snapshot.score);
+    for byte in rng.get_seed() {
+        let _ = write!(text, "{byte:02X}");
+    }
+    let _ = write!(text, " {} {}", rng.get_stream(), rng.get_word_pos());
+    for cell in snapshot.cells.iter().flatten() {
+        let _ = write!(text, " {}", cell.exponent());
+    }
+    text
+}
+
+fn decode_snapshot(text: &str, size: usize) -> io::Result<Snapshot> {
+    let mut items = text.split_whitespace();
+    let mut next = |message: &str| items.next().ok_or_else(|| invalid(message));
+
+    let score = next("missing score")?
+        .parse()
+        .map_err(|_| invalid("bad score"))?;
+
+    let hex = next("missing rng seed")?;
+    let mut rng_seed = <GameRng as SeedableRng>::Seed::default();
+    if hex.len() != rng_seed.len() * 2 || !hex.is_ascii() {
+        return Err(invalid("bad rng seed"));
+    }
+    for (i, byte) in rng_seed.iter_mut().enumerate() {
+        *byte =
+            u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid("bad rng seed"))?;
+    }
+    let stream = next("missing rng stream")?
+        .parse()
+        .map_err(|_| invalid("bad rng stream"))?;
+    let word_pos = next("missing rng position")?
+        .parse()
+        .map_err(|_| invalid("bad rng position"))?;
+    let mut rng = GameRng::from_seed(rng_seed);
+    rng.set_stream(stream);
+    rng.set_word_pos(word_pos);
+
+    let mut cells: Grid = new_grid(size);
+    for cell in cells.iter_mut().flatten() {
+        let exponent = next("missing cell")?
+            .parse()
+            .map_err(|_| invalid("bad cell"))?;
+        *cell = Cell::new(exponent);
+    }
+    Ok(Snapshot { cells, rng, score })
+}
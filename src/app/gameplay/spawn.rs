@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use rand::{Rng, RngCore};
+
+/// 新地块数值的生成策略，数值都以指数表示
+pub trait SpawnPolicy: Send + Sync {
+    /// 返回 0 时不生成地块
+    fn spawn_exponent(&self, rng: &mut dyn RngCore) -> u8;
+
+    /// 每种指数出现的概率，AI 搜索时用来展开随机节点
+    fn distribution(&self) -> Vec<(u8, f64)>;
+
+    /// 写入录像的描述，[`from_descriptor`] 需要能把它还原回来
+    fn descriptor(&self) -> String;
+}
+
+/// 描述来自录像、存档和用户的谜题文件，概率不在 0 到 1 之间时视为无效
+pub fn from_descriptor(descriptor: &str) -> Option<Arc<dyn SpawnPolicy>> {
+    let (name, param) = descriptor.split_once(':').unwrap_or((descriptor, ""));
+    match name {
+        "standard" => Some(Arc::new(StandardSpawn {
+            four_probability: param.parse().ok().filter(|x| (0.0..=1.0).contains(x))?,
+        })),
+        "none" => Some(Arc::new(NoSpawn)),
+        _ => None,
+    }
+}
+
+/// 经典规则：大部分时候生成 2，少数时候生成 4
+pub struct StandardSpawn {
+    pub four_probability: f64,
+}
+
+impl Default for StandardSpawn {
+    fn default() -> Self {
+        Self {
+            four_probability: 0.1,
+        }
+    }
+}
+
+impl SpawnPolicy for StandardSpawn {
+    fn spawn_exponent(&self, rng: &mut dyn RngCore) -> u8 {
+        if rng.gen_bool(self.four_probability.clamp(0.0, 1.0)) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn distribution(&self) -> Vec<(u8, f64)> {
+        let p = self.four_probability.clamp(0.0, 1.0);
+        vec![(1, 1.0 - p), (2, p)]
+    }
+
+    fn descriptor(&self) -> String {
+        format!("standard:{}", self.four_probability)
+    }
+}
+
+/// 移动后不生成新地块，谜题用它摆出确定的局面
+pub struct NoSpawn;
+
+impl SpawnPolicy for NoSpawn {
+    fn spawn_exponent(&self, _: &mut dyn RngCore) -> u8 {
+        0
+    }
+
+    fn distribution(&self) -> Vec<(u8, f64)> {
+        Vec::new()
+    }
+
+    fn descriptor(&self) -> String {
+        String::from("none")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptor_round_trip() {
+        let policy = from_descriptor("standard:0.25").unwrap();
+        assert_eq!(policy.descriptor(), "standard:0.25");
+        assert_eq!(from_descriptor("none").unwrap().descriptor(), "none");
+    }
+
+    #[test]
+    fn rejects_invalid_probability() {
+        for x in [
+            "standard:NaN",
+            "standard:inf",
+            "standard:-0.1",
+            "standard:1.5",
+            "standard:",
+        ] {
+            assert!(from_descriptor(x).is_none(), "{x}");
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{LazyLock, RwLock},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::utils::data_dir;
+
+/// 用户按键配置的文件名，放在数据目录下
+pub const KEYMAP_FILE: &str = "keymap.conf";
+
+/// 需要按键的界面，每个界面有自己的一组操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Screen {
+    Menu,
+    Gameplay,
+    Ranking,
+    Manage,
+    Puzzles,
+    Sandbox,
+    Replay,
+}
+
+impl Screen {
+    pub const ALL: [Screen; 7] = [
+        Screen::Menu,
+        Screen::Gameplay,
+        Screen::Ranking,
+        Screen::Manage,
+        Screen::Puzzles,
+        Screen::Sandbox,
+        Screen::Replay,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Screen::Menu => "menu",
+            Screen::Gameplay => "gameplay",
+            Screen::Ranking => "ranking",
+            Screen::Manage => "manage",
+            Screen::Puzzles => "puzzles",
+            Screen::Sandbox => "sandbox",
+            Screen::Replay => "replay",
+        }
+    }
+
+    /// 界面上的操作和默认按键，排在前面的操作在按键冲突时优先
+    fn defaults(self) -> &'static [(Action, &'static [&'static str])] {
+        const NAVIGATION: [(Action, &[&str]); 4] = [
+            (Action::Up, &["up"]),
+            (Action::Down, &["down"]),
+            (Action::Left, &["left"]),
+            (Action::Right, &["right"]),
+        ];
+        match self {
+            Screen::Menu => &[
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+                (Action::Confirm, &["enter"]),
+                (Action::Quit, &["q", "esc"]),
+            ],
+            Screen::Gameplay => &[
+                (Action::Quit, &["q", "esc"]),
+                (Action::Seed, &["s"]),
+                (Action::Hint, &["h"]),
+                (Action::Undo, &["u"]),
+                (Action::Redo, &["r"]),
+                (Action::Pause, &["space", "p"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+            ],
+            Screen::Ranking => &[
+                (Action::Quit, &["q", "esc"]),
+                (Action::Top, &["ctrl+up"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+                (Action::Mode, &["tab"]),
+                (Action::Confirm, &["enter"]),
+            ],
+            Screen::Manage => &[
+                (Action::Quit, &["q", "esc"]),
+                (Action::Switch, &["s"]),
+                (Action::Rename, &["r"]),
+                (Action::Delete, &["d"]),
+                (Action::Verify, &["v"]),
+                (Action::Info, &["i"]),
+                (Action::Replay, &["p"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+                (Action::Confirm, &["enter"]),
+            ],
+            Screen::Puzzles => &[
+                (Action::Quit, &["q", "esc"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                (Action::Confirm, &["enter"]),
+            ],
+            Screen::Sandbox => &[
+                (Action::Quit, &["q", "esc"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+                (Action::Increase, &["+", "="]),
+                (Action::Decrease, &["-"]),
+                (Action::Erase, &["0", "delete", "backspace"]),
+                (Action::Clear, &["c"]),
+                (Action::Smaller, &["["]),
+                (Action::Larger, &["]"]),
+                (Action::Goal, &["g"]),
+                (Action::Moves, &["m"]),
+                (Action::Confirm, &["enter", "p"]),
+                (Action::Export, &["e"]),
+            ],
+            Screen::Replay => &[
+                (Action::Quit, &["q", "esc"]),
+                (Action::Pause, &["space"]),
+                NAVIGATION[0],
+                NAVIGATION[1],
+                NAVIGATION[2],
+                NAVIGATION[3],
+                (Action::Back, &["pageup"]),
+                (Action::Forward, &["pagedown"]),
+                (Action::Top, &["home"]),
+                (Action::End, &["end"]),
+            ],
+        }
+    }
+}
+
+/// 有名字的操作，配置文件里用名字指代
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Quit,
+    /// 游戏：输入种子
+    Seed,
+    /// 游戏：提示
+    Hint,
+    Undo,
+    Redo,
+    /// 游戏：暂停，机器人演示时暂停机器人，回放：播放或暂停
+    Pause,
+    /// 排名：回到第一行，回放：跳到开头
+    Top,
+    /// 排名：切换模式
+    Mode,
+    /// 管理：切换玩家
+    Switch,
+    Rename,
+    Delete,
+    Verify,
+    Info,
+    /// 管理：观看录像
+    Replay,
+    /// 沙盒：增大选中的地块
+    Increase,
+    Decrease,
+    /// 沙盒：清除选中的地块
+    Erase,
+    /// 沙盒：清空棋盘
+    Clear,
+    /// 沙盒：缩小棋盘
+    Smaller,
+    Larger,
+    /// 沙盒：切换导出谜题的目标
+    Goal,
+    /// 沙盒：切换导出谜题的步数限制
+    Moves,
+    /// 沙盒：导出为谜题
+    Export,
+    /// 回放：快退
+    Back,
+    Forward,
+    /// 回放：跳到结尾
+    End,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Confirm => "confirm",
+            Action::Quit => "quit",
+            Action::Seed => "seed",
+            Action::Hint => "hint",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Pause => "pause",
+            Action::Top => "top",
+            Action::Mode => "mode",
+            Action::Switch => "switch",
+            Action::Rename => "rename",
+            Action::Delete => "delete",
+            Action::Verify => "verify",
+            Action::Info => "info",
+            Action::Replay => "replay",
+            Action::Increase => "increase",
+            Action::Decrease => "decrease",
+            Action::Erase => "erase",
+            Action::Clear => "clear",
+            Action::Smaller => "smaller",
+            Action::Larger => "larger",
+            Action::Goal => "goal",
+            Action::Moves => "moves",
+            Action::Export => "export",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::End => "end",
+        }
+    }
+}
+
+/// 一个按键，可以带上 Ctrl
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+impl KeyBinding {
+    /// 解析 `up`、`enter`、`q`、`ctrl+up` 这样的按键名
+    pub fn parse(text: &str) -> Option<Self> {
+        let lower = text.to_ascii_lowercase();
+        let (ctrl, name) = match lower.strip_prefix("ctrl+") {
+            Some(rest) => (true, &text[text.len() - rest.len()..]),
+            None => (false, text),
+        };
+        let code = match name.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if !c.is_whitespace() => KeyCode::Char(c),
+                    _ => return None,
+                }
+            }
+        };
+        Some(Self { code, ctrl })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        self.code == key.code && self.ctrl == key.modifiers.contains(KeyModifiers::CONTROL)
+    }
+
+    /// 显示在界面提示里的写法
+    pub fn label(&self) -> String {
+        let name = match self.code {
+            KeyCode::Up => String::from("↑"),
+            KeyCode::Down => String::from("↓"),
+            KeyCode::Left => String::from("←"),
+            KeyCode::Right => String::from("→"),
+            KeyCode::Enter => String::from("⏎"),
+            KeyCode::Esc => String::from("Esc"),
+            KeyCode::Tab => String::from("Tab"),
+            KeyCode::Char(' ') => String::from("空格"),
+            KeyCode::Char(c) => c.to_uppercase().to_string(),
+            KeyCode::Backspace => String::from("Backspace"),
+            KeyCode::Delete => String::from("Del"),
+            KeyCode::Home => String::from("Home"),
+            KeyCode::End => String::from("End"),
+            KeyCode::PageUp => String::from("PgUp"),
+            KeyCode::PageDown => String::from("PgDn"),
+            _ => String::from("?"),
+        };
+        if self.ctrl {
+            format!("Ctrl + {name}")
+        } else {
+            name
+        }
+    }
+}
+
+/// 内置的按键方案，写法和配置文件相同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Arrows,
+    Wasd,
+    Vim,
+}
+
+impl Preset {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "arrows" => Some(Preset::Arrows),
+            "wasd" => Some(Preset::Wasd),
+            "vim" => Some(Preset::Vim),
+            _ => None,
+        }
+    }
+
+    fn overrides(self) -> &'static str {
+        match self {
+            Preset::Arrows => "",
+            Preset::Wasd => indoc::indoc! {"
+                *.up up w
+                *.down down s
+                *.left left a
+                *.right right d
+                gameplay.seed e
+                manage.switch tab
+                manage.delete x
+            "},
+            Preset::Vim => indoc::indoc! {"
+                *.up up k
+                *.down down j
+                *.left left h
+                *.right right l
+                gameplay.hint ?
+                ranking.top ctrl+up g
+            "},
+        }
+    }
+}
+
+/// 各个界面的按键到操作的映射
+pub struct Keymap {
+    bindings: HashMap<(Screen, Action), Vec<KeyBinding>>,
+    /// 配置文件中无法理解的行
+    pub warnings: Vec<String>,
+}
+
+impl Keymap {
+    pub fn new(preset: Preset) -> Self {
+        let mut bindings = HashMap::new();
+        for screen in Screen::ALL {
+            for (action, keys) in screen.defaults() {
+                let keys = keys.iter().filter_map(|x| KeyBinding::parse(x)).collect();
+                bindings.insert((screen, *action), keys);
+            }
+        }
+        let mut this = Self {
+            bindings,
+            warnings: Vec::new(),
+        };
+        this.apply(preset.overrides());
+        this
+    }
+
+    /// 读取数据目录下的配置文件，没有配置文件时使用方向键方案
+    pub fn load() -> Self {
+        let text = match fs::read_to_string(keymap_path()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::new(Preset::Arrows),
+            Err(e) => {
+                let mut this = Self::new(Preset::Arrows);
+                this.warnings.push(e.to_string());
+                return this;
+            }
+        };
+        Self::parse(&text)
+    }
+
+    /// 配置文件每行一条，`#` 开头的行是注释：
+    ///
+    /// ```text
+    /// preset vim
+    /// gameplay.undo u z
+    /// *.quit q esc
+    /// ```
+    ///
+    /// `preset` 选择内置方案，只能写在最前面；其余的行把某个界面的操作换成后面列出的按键，
+    /// 界面写成 `*` 表示所有有这个操作的界面
+    pub fn parse(text: &str) -> Self {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty() && !x.starts_with('#'))
+            .peekable();
+        let mut warnings = Vec::new();
+        let mut preset = Preset::Arrows;
+        if let Some(value) = lines.peek().and_then(|x| x.strip_prefix("preset ")) {
+            match Preset::parse(value.trim()) {
+                Some(x) => preset = x,
+                None => warnings.push(format!("未知的按键方案: {}", value.trim())),
+            }
+            lines.next();
+        }
+
+        let mut this = Self::new(preset);
+        this.warnings = warnings;
+        for line in lines {
+            this.apply_line(line);
+        }
+        this
+    }
+
+    fn apply(&mut self, text: &str) {
+        for line in text.lines().filter(|x| !x.trim().is_empty()) {
+            self.apply_line(line.trim());
+        }
+    }
+
+    fn apply_line(&mut self, line: &str) {
+        let mut parts = line.split_whitespace();
+        let Some((screen, action)) = parts.next().and_then(|x| x.split_once('.')) else {
+            self.warnings.push(format!("无法理解: {line}"));
+            return;
+        };
+        let mut keys = Vec::new();
+        for part in parts {
+            match KeyBinding::parse(part) {
+                Some(key) => keys.push(key),
+                None => {
+                    self.warnings.push(format!("未知的按键: {part}"));
+                    return;
+                }
+            }
+        }
+
+        let targets = self
+            .bindings
+            .keys()
+            .filter(|(s, a)| (screen == "*" || s.name() == screen) && a.name() == action)
+            .copied()
+            .collect::<Vec<_>>();
+        if targets.is_empty() {
+            self.warnings.push(format!("未知的操作: {screen}.{action}"));
+            return;
+        }
+        for target in targets {
+            self.bindings.insert(target, keys.clone());
+        }
+    }
+
+    /// 按键在这个界面上对应的操作
+    pub fn action(&self, screen: Screen, key: &KeyEvent) -> Option<Action> {
+        screen
+            .defaults()
+            .iter()
+            .map(|(action, _)| *action)
+            .find(|action| {
+                self.bindings
+                    .get(&(screen, *action))
+                    .is_some_and(|keys| keys.iter().any(|x| x.matches(key)))
+            })
+    }
+
+    /// 操作的第一个按键的写法，没有按键时为空
+    pub fn label(&self, screen: Screen, action: Action) -> String {
+        self.bindings
+            .get(&(screen, action))
+            .and_then(|x| x.first())
+            .map_or(String::new(), |x| x.label())
+    }
+}
+
+pub fn keymap_path() -> PathBuf {
+    data_dir().join(KEYMAP_FILE)
+}
+
+pub static KEYMAP: LazyLock<RwLock<Keymap>> = LazyLock::new(|| RwLock::new(Keymap::load()));
+
+pub fn action(screen: Screen, key: &KeyEvent) -> Option<Action> {
+    KEYMAP.read().unwrap().action(screen, key)
+}
+
+pub fn label(screen: Screen, action: Action) -> String {
+    KEYMAP.read().unwrap().label(screen, action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn parses_keys_with_ctrl() {
+        let binding = KeyBinding::parse("ctrl+up").unwrap();
+        assert_eq!(binding, KeyBinding::parse("Ctrl+Up").unwrap());
+        assert!(binding.ctrl && binding.code == KeyCode::Up);
+        assert!(binding.matches(&KeyEvent::new(KeyCode::Up, KeyModifiers::CONTROL)));
+        assert!(!binding.matches(&key(KeyCode::Up)));
+        assert_eq!(binding.label(), "Ctrl + ↑");
+
+        assert_eq!(KeyBinding::parse("?").unwrap().code, KeyCode::Char('?'));
+        assert_eq!(KeyBinding::parse("ctrl+"), None);
+        assert_eq!(KeyBinding::parse("qq"), None);
+    }
+
+    #[test]
+    fn overrides_replace_default_keys() {
+        let keymap = Keymap::parse("# 注释\npreset wasd\ngameplay.undo u z\n*.quit x\n");
+        assert!(keymap.warnings.is_empty());
+        let undo = keymap.action(Screen::Gameplay, &key(KeyCode::Char('z')));
+        assert_eq!(undo, Some(Action::Undo));
+        let up = keymap.action(Screen::Gameplay, &key(KeyCode::Char('w')));
+        assert_eq!(up, Some(Action::Up));
+        for screen in Screen::ALL {
+            assert_eq!(
+                keymap.action(screen, &key(KeyCode::Char('x'))),
+                Some(Action::Quit)
+            );
+            assert_eq!(keymap.action(screen, &key(KeyCode::Esc)), None);
+        }
+    }
+
+    #[test]
+    fn bad_lines_are_reported_and_skipped() {
+        let text = "preset emacs\nnonsense\ngameplay.fly f\ngameplay.undo z ctrl+\npreset vim\n";
+        let keymap = Keymap::parse(text);
+        assert_eq!(keymap.warnings.len(), 5);
+        // 按键有误的整行都不生效，方案也还是默认的方向键
+        let undo = keymap.action(Screen::Gameplay, &key(KeyCode::Char('u')));
+        assert_eq!(undo, Some(Action::Undo));
+        let h = keymap.action(Screen::Gameplay, &key(KeyCode::Char('h')));
+        assert_eq!(h, Some(Action::Hint));
+    }
+
+    #[test]
+    fn presets_have_no_conflicts() {
+        for preset in [Preset::Arrows, Preset::Wasd, Preset::Vim] {
+            let keymap = Keymap::new(preset);
+            assert!(keymap.warnings.is_empty(), "{preset:?}");
+            for screen in Screen::ALL {
+                let mut seen: Vec<(KeyBinding, Action)> = Vec::new();
+                for (action, _) in screen.defaults() {
+                    for binding in &keymap.bindings[&(screen, *action)] {
+                        if let Some((_, other)) = seen.iter().find(|(x, _)| x == binding) {
+                            panic!(
+                                "{preset:?} {}: {} 同时绑定了 {} 和 {}",
+                                screen.name(),
+                                binding.label(),
+                                other.name(),
+                                action.name()
+                            );
+                        }
+                        seen.push((*binding, *action));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vim_moves_hint_off_h() {
+        let keymap = Keymap::new(Preset::Vim);
+        let h = key(KeyCode::Char('h'));
+        for screen in [Screen::Gameplay, Screen::Sandbox, Screen::Manage] {
+            assert_eq!(keymap.action(screen, &h), Some(Action::Left));
+        }
+        let hint = keymap.action(Screen::Gameplay, &key(KeyCode::Char('?')));
+        assert_eq!(hint, Some(Action::Hint));
+        assert_eq!(keymap.label(Screen::Gameplay, Action::Hint), "?");
+    }
+}
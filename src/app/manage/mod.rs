@@ -15,7 +15,13 @@ use tui_textarea::TextArea;
 
 use crate::data_manager;
 
-use super::{screens::Activity, structs::Player, time::TIME, utils::fade_in};
+use super::{
+    data::{DataError, RETRY_INTERVAL},
+    screens::Activity,
+    structs::Player,
+    time::TIME,
+    utils::fade_in,
+};
 
 // TODO: Ranking控件，实现搜索，和选择条目
 #[derive(Default)]
@@ -35,6 +41,8 @@ pub struct PlayerListSelector<'a> {
     table_state: TableState,
     players: Vec<Player>,
     players_requested: bool,
+    /// 请求失败后，到这个时间再重试
+    retry_time: Duration,
     player_rows: Vec<Row<'a>>,
     player_columns_longest: (u16, u16, u16, u16),
     scroll_state: ScrollbarState,
@@ -86,7 +94,7 @@ impl PlayerListSelector<'_> {
         let [search_bar, result_bar] =
             Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(content);
 
-        let cursor_color = if self.app_time.as_secs() % 2 == 0 {
+        let cursor_color = if self.app_time.as_secs().is_multiple_of(2) {
             fg
         } else {
             Color::Reset
@@ -347,6 +355,22 @@ impl PlayerListSelector<'_> {
     }
 }
 
+/// 请求失败时推迟到稍后重试，不能把失败当成没有玩家
+fn retry_later<T>(
+    result: Option<Result<T, DataError>>,
+    now: Duration,
+    retry_time: &mut Duration,
+) -> Option<T> {
+    match result {
+        Some(Ok(x)) => Some(x),
+        Some(Err(_)) => {
+            *retry_time = now + RETRY_INTERVAL;
+            None
+        }
+        None => None,
+    }
+}
+
 impl Activity for PlayerListSelector<'_> {
     fn draw(&mut self, frame: &mut Frame<'_>) {
         let area = frame.area();
@@ -371,46 +395,55 @@ impl Activity for PlayerListSelector<'_> {
             self.app_time += time.delta;
         }
 
-        if !self.players_requested {
-            if let Some(players) = data_manager!(get_players) {
-                let mut buffer = itoa::Buffer::new();
-                self.players_requested = true;
-                self.player_rows = players
-                    .clone()
+        if !self.players_requested
+            && self.app_time >= self.retry_time
+            && let Some(players) = retry_later(
+                data_manager!(get_players),
+                self.app_time,
+                &mut self.retry_time,
+            )
+        {
+            let mut buffer = itoa::Buffer::new();
+            self.players_requested = true;
+            self.player_rows = players
+                .clone()
+                .into_iter()
+                .map(|x| {
+                    let time = if x.best_timestamp != 0 {
+                        (chrono::Utc.timestamp_millis_opt(x.best_timestamp).unwrap()
+                            + chrono::Duration::hours(8))
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                    } else {
+                        String::from("无")
+                    };
+                    [
+                        Cell::from(buffer.format(x.id).to_string()),
+                        Cell::from(x.name),
+                        Cell::from(buffer.format(x.best_score).to_string()),
+                        Cell::from(buffer.format(x.best_time).to_string()),
+                        Cell::from(time),
+                    ]
                     .into_iter()
-                    .map(|x| {
-                        let time = if x.best_timestamp != 0 {
-                            (chrono::Utc.timestamp_millis_opt(x.best_timestamp).unwrap()
-                                + chrono::Duration::hours(8))
-                            .format("%Y-%m-%d %H:%M:%S")
-                            .to_string()
-                        } else {
-                            String::from("无")
-                        };
-                        [
-                            Cell::from(buffer.format(x.id).to_string()),
-                            Cell::from(x.name),
-                            Cell::from(buffer.format(x.best_score).to_string()),
-                            Cell::from(buffer.format(x.best_time).to_string()),
-                            Cell::from(time),
-                        ]
-                        .into_iter()
-                        .collect::<Row>()
-                    })
-                    .collect::<Vec<_>>();
-                self.calculate_players_columns_longest();
-                self.scroll_state = self.scroll_state.content_length(players.len());
-                self.players = players;
-            }
+                    .collect::<Row>()
+                })
+                .collect::<Vec<_>>();
+            self.calculate_players_columns_longest();
+            self.scroll_state = self.scroll_state.content_length(players.len());
+            self.players = players;
         }
-        if self.last_search_text != self.search_bar.lines()[0] {
+        if self.last_search_text != self.search_bar.lines()[0] && self.app_time >= self.retry_time {
             let search_text = &self.search_bar.lines()[0];
             let player = Player {
                 id: search_text.parse::<i32>().unwrap_or_default(),
                 name: search_text.clone(),
                 ..Default::default()
             };
-            if let Some(players) = data_manager!(find_player, player) {
+            if let Some(players) = retry_later(
+                data_manager!(find_player, player),
+                self.app_time,
+                &mut self.retry_time,
+            ) {
                 let mut buffer = itoa::Buffer::new();
                 self.search_result_rows = players
                     .clone()
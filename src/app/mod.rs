@@ -2,6 +2,7 @@ pub(crate) mod ascii;
 pub(crate) mod data;
 pub(crate) mod entry;
 pub(crate) mod gameplay;
+pub(crate) mod keymap;
 pub(crate) mod math;
 pub(crate) mod screens;
 pub(crate) mod structs;
@@ -0,0 +1,192 @@
+use std::rc::Rc;
+
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::Style,
+    text::Text,
+    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph},
+};
+
+use crate::app::{
+    gameplay::{Grid, colors, new_grid},
+    math::{Interpolation, inverse_lerp},
+    structs::*,
+    time::TIME,
+    utils::{rect_move, rect_scale},
+};
+
+/// 棋盘和地块动画的绘制，游戏界面和录像回放共用
+pub struct BoardView {
+    visual_cells: Grid,
+    animations: Vec<AnimationCell>,
+    itoa_buffer: itoa::Buffer,
+}
+
+impl BoardView {
+    pub fn new(size: usize, animations: Vec<AnimationCell>) -> Self {
+        Self {
+            visual_cells: new_grid(size),
+            animations,
+            itoa_buffer: itoa::Buffer::new(),
+        }
+    }
+
+    /// 播放一次移动产生的动画，会替换掉尚未播放完的动画
+    pub fn animate(&mut self, animations: Vec<AnimationCell>) {
+        self.animations = animations;
+    }
+
+    /// 跳过动画，直接显示 `cells`
+    pub fn snap(&mut self, cells: &Grid) {
+        self.visual_cells = cells.clone();
+        self.animations.clear();
+    }
+
+    pub fn gen_block(itoa_buffer: &mut itoa::Buffer, cell: Cell, rect: Rect) -> Paragraph<'_> {
+        // 左右边框和右侧的留白
+        let width = rect.width.saturating_sub(3) as usize;
+        let block_text = if cell.empty() {
+            Text::from(" ")
+        } else {
+            match cell.value() {
+                u64::MAX => Text::from(short_label(cell, width)),
+                value => {
+                    let text = itoa_buffer.format(value);
+                    if text.len() <= width {
+                        Text::from(text)
+                    } else {
+                        Text::from(short_label(cell, width))
+                    }
+                }
+            }
+        };
+        Paragraph::new(block_text)
+            .style(Style::default().fg(colors::color_setter(cell.exponent())))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .padding(Padding::new(0, 1, rect.height / 3, 1)),
+            )
+            .alignment(Alignment::Center)
+    }
+
+    /// 把 `area` 切成 `size`×`size` 个格子，`cols[x][y]` 对应 `cells[x][y]`
+    pub fn layout(area: Rect, size: usize) -> Vec<Rc<[Rect]>> {
+        Layout::vertical(vec![Constraint::Ratio(1, size as u32); size])
+            .split(area)
+            .iter()
+            .map(|x| Layout::horizontal(vec![Constraint::Ratio(1, size as u32); size]).split(*x))
+            .collect()
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame<'_>, area: Rect, cells: &Grid) {
+        let exp_out = Interpolation::ExpOut { value: 20.0 };
+        let size = cells.len();
+        let cols = Self::layout(area, size);
+
+        {
+            let cell = Paragraph::default()
+                .style(Style::default().fg(colors::color_setter(0)))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded),
+                );
+            for rows in &cols {
+                for row in rows.iter() {
+                    frame.render_widget(&cell, *row);
+                }
+            }
+        }
+
+        // Popup和Move动画完成后，将cells复制到visual_cells
+        self.animations.retain(|x| {
+            (matches!(x.animation_type, CellAnimationType::Popup) && x.duration.as_secs_f32() < 0.3)
+                || (matches!(x.animation_type, CellAnimationType::Move)
+                    && x.duration.as_secs_f32() < 0.2)
+        });
+
+        // 从显示列表删除地块
+        for cell in &self.animations {
+            self.visual_cells[cell.src.x][cell.src.y] = Cell::default();
+        }
+        // 播放动画
+        for cell in &self.animations {
+            match cell.animation_type {
+                CellAnimationType::Popup => {
+                    let progress = inverse_lerp(0.0..=0.8_f32, cell.duration.as_secs_f32());
+                    let rect = rect_scale(cols[cell.src.x][cell.src.y], exp_out.apply(progress));
+                    frame.render_widget(Clear, rect);
+                    frame.render_widget(
+                        Self::gen_block(&mut self.itoa_buffer, cell.cell, rect),
+                        rect,
+                    );
+                }
+                CellAnimationType::Move => {
+                    let progress = inverse_lerp(0.0..=0.6_f32, cell.duration.as_secs_f32());
+                    let dest = cell.dest.as_ref().unwrap();
+                    let rect = rect_move(
+                        cols[cell.src.x][cell.src.y],
+                        cols[dest.x][dest.y],
+                        exp_out.apply(progress),
+                    );
+                    frame.render_widget(Clear, rect);
+                    frame.render_widget(
+                        Self::gen_block(&mut self.itoa_buffer, cell.cell, rect),
+                        rect,
+                    );
+                }
+            }
+        }
+        // 动画播放完成后，把判断列表里的地块全部复制到显示列表
+        for (i, row) in cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if !self.animations.iter().any(|x| {
+                    if matches!(x.animation_type, CellAnimationType::Move) {
+                        let dest = x.dest.unwrap();
+                        dest.x == i && dest.y == j
+                    } else {
+                        x.src.x == i && x.src.y == j
+                    }
+                }) {
+                    self.visual_cells[i][j] = *cell;
+                }
+            }
+        }
+
+        {
+            let time = TIME.read().unwrap();
+            for cell in &mut self.animations {
+                cell.duration += time.delta;
+            }
+        }
+
+        for (row, rects) in self.visual_cells.iter().zip(&cols) {
+            for (cell, rect) in row.iter().zip(rects.iter()) {
+                if cell.empty() {
+                    continue;
+                }
+                frame.render_widget(Self::gen_block(&mut self.itoa_buffer, *cell, *rect), *rect);
+            }
+        }
+    }
+}
+
+/// 数值的后缀，每一级是 2 的 10 次方
+const SUFFIXES: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+
+/// 完整的数值放不下时的写法：先尝试 `64K` 这样的缩写，再退到 `2^16`
+fn short_label(cell: Cell, width: usize) -> String {
+    let exponent = cell.exponent() as usize;
+    if exponent >= 10
+        && let Some(suffix) = SUFFIXES.get(exponent / 10 - 1)
+    {
+        let label = format!("{}{suffix}", 1 << (exponent % 10));
+        if label.len() <= width {
+            return label;
+        }
+    }
+    format!("2^{exponent}")
+}
@@ -1,157 +1,748 @@
 use std::{
-    rc::Rc,
-    sync::{Arc, atomic::AtomicI8},
+    sync::{
+        Arc,
+        atomic::AtomicI8,
+        mpsc::{self, Receiver},
+    },
+    thread,
     time::{Duration, Instant},
 };
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
 use rand::Rng;
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Flex, Layout, Rect},
-    style::{Style, palette::tailwind},
+    style::{Color, Style, Stylize, palette::tailwind},
     widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph},
 };
+use tui_textarea::TextArea;
 
 use crate::{
     app::{
         ascii,
-        gameplay::{movement::*, *},
-        math::{Interpolation, inverse_lerp, lerpf},
+        data::RETRY_INTERVAL,
+        gameplay::{
+            ai::{self, Hint},
+            bot::{STRATEGIES, Strategy},
+            game::Game,
+            puzzle::Puzzle,
+            replay::{Replay, ReplayAction, bot_replay_path, replay_path},
+            save::SavedGame,
+            *,
+        },
+        keymap::{self, Action, Screen},
+        math::lerpf,
         structs::*,
         time::TIME,
-        utils::{fade_in, get_time_millis, rect_move, rect_scale},
+        utils::{fade_in, get_time_millis},
     },
     data_manager,
 };
 
 use super::{
     Activity,
+    board::BoardView,
     dialog::{DIALOG_MANAGER, Dialog},
 };
 
-#[derive(Default)]
+/// AI 提示的状态
+enum HintState {
+    Idle,
+    /// 搜索在后台线程进行，不阻塞绘制
+    Searching(Receiver<Option<Hint>>),
+    Ready(Hint),
+    /// 没有能移动的方向
+    NoMove,
+}
+
+/// 机器人每一步之间的间隔
+const AUTOPLAY_INTERVALS: [Duration; 5] = [
+    Duration::from_millis(1000),
+    Duration::from_millis(500),
+    Duration::from_millis(250),
+    Duration::from_millis(100),
+    Duration::from_millis(30),
+];
+const DEFAULT_AUTOPLAY_INTERVAL: usize = 2;
+
+/// 鼠标拖动超过这么多行才算一次滑动，横向按两列一行折算
+const SWIPE_THRESHOLD: i32 = 2;
+
+type StrategyResult = (Box<dyn Strategy>, Option<CellMotionDirection>);
+
+/// 机器人自动游玩的状态
+struct Autoplay {
+    /// `STRATEGIES` 的下标
+    index: usize,
+    /// 策略在后台线程思考时由线程持有，想好后连同结果一起送回
+    strategy: Option<Box<dyn Strategy>>,
+    thinking: Option<Receiver<StrategyResult>>,
+    interval: usize,
+    paused: bool,
+    wait: Duration,
+}
+
+impl Autoplay {
+    fn new(index: usize, seed: u64) -> Self {
+        Self {
+            index,
+            strategy: Some(STRATEGIES[index].1(seed)),
+            thinking: None,
+            interval: DEFAULT_AUTOPLAY_INTERVAL,
+            paused: false,
+            wait: Duration::ZERO,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        STRATEGIES[self.index].0
+    }
+}
+
+/// 等待写入数据层的存档操作
+enum SaveAction {
+    Write(String),
+    /// 继续的那一局已经结束，删除存档
+    Clear,
+}
+
 pub struct GameplayActivity {
-    cells: Grid,
-    visual_cells: Grid,
-    score: i32,
-    show_score: i32,
+    game: Game,
+    hint: HintState,
+    autoplay: Option<Autoplay>,
+    seed_input: Option<TextArea<'static>>,
+    replay: Replay,
+    board: BoardView,
+    show_score: u64,
     high_score: Player,
     player_requested: bool,
+    /// 查询当前玩家失败后，到这个时间再重试
+    player_retry_time: Duration,
     pub record_saved: bool,
     play_time: Duration,
     app_time: Duration,
     play_started: bool,
-    itoa_buffer: itoa::Buffer,
-    animations: Vec<AnimationCell>,
 
     pub should_exit: bool,
-    pub game_over: bool,
     dead_dialog: bool,
     dead_dialog_chose: Arc<AtomicI8>,
     dead_time: i64,
     pub show_ranking: bool,
 
     dead_dialog_time: Duration,
+    /// 合成目标地块后，等动画播放完再弹出胜利对话框
+    win_dialog_time: Option<Duration>,
+    win_dialog_chose: Arc<AtomicI8>,
+    /// 暂停时计时停止，棋盘也被遮住
+    paused: bool,
+    pause_dialog_chose: Arc<AtomicI8>,
+
+    /// 从存档继续的一局，结束时要删除存档
+    resumed: bool,
+    pending_save: Option<SaveAction>,
+    /// 正在解的谜题
+    puzzle: Option<Puzzle>,
+    /// 沙盒里摆出的开局局面，重试时回到这里
+    sandbox: Option<Grid>,
+    /// 上一帧棋盘所在的位置，只有在棋盘上按下的拖动才算滑动
+    board_area: Rect,
+    /// 鼠标按下的位置，滑动一次后清空
+    drag_start: Option<(u16, u16)>,
 }
 
 impl GameplayActivity {
-    pub fn new() -> Self {
-        // 到这里应该早就被初始化了
-        let mut this = Self {
-            cells: vec![vec![Cell::default(); 4]; 4],
-            visual_cells: vec![vec![Cell::default(); 4]; 4],
+    pub fn new(config: GameConfig, seed: u64) -> Self {
+        Self::with_game(Game::new(config, seed))
+    }
+
+    fn with_game(game: Game) -> Self {
+        Self {
+            board: BoardView::new(game.board_size(), game.popup_animations()),
+            replay: Replay::new(game.config(), game.seed()),
+            game,
+            hint: HintState::Idle,
+            autoplay: None,
+            seed_input: None,
+            show_score: 0,
+            high_score: Player::default(),
+            player_requested: false,
+            player_retry_time: Duration::default(),
+            record_saved: false,
+            play_time: Duration::default(),
+            app_time: Duration::default(),
+            play_started: false,
+            should_exit: false,
+            dead_dialog: false,
             dead_dialog_chose: Arc::new(AtomicI8::new(-1)),
-            ..Default::default()
-        };
+            dead_time: 0,
+            show_ranking: false,
+            dead_dialog_time: Duration::default(),
+            win_dialog_time: None,
+            win_dialog_chose: Arc::new(AtomicI8::new(-1)),
+            paused: false,
+            pause_dialog_chose: Arc::new(AtomicI8::new(-1)),
+            resumed: false,
+            pending_save: None,
+            puzzle: None,
+            sandbox: None,
+            board_area: Rect::default(),
+            drag_start: None,
+        }
+    }
+
+    /// 继续上次中途退出的一局
+    pub fn resume(saved: SavedGame) -> Self {
+        let SavedGame {
+            game,
+            play_time,
+            replay,
+        } = saved;
+        let mut this = Self::new(game.config().clone(), game.seed());
+        this.board = BoardView::new(game.board_size(), Vec::new());
+        this.board.snap(game.cells());
+        this.show_score = game.score();
+        this.game = game;
+        this.replay = replay;
+        this.play_time = play_time;
+        this.play_started = true;
+        this.resumed = true;
+        this
+    }
+
+    /// 由机器人游玩的一局，成绩不会计入玩家的记录
+    pub fn autoplay(config: GameConfig, seed: u64, strategy: usize) -> Self {
+        let mut this = Self::new(config, seed);
+        this.autoplay = Some(Autoplay::new(strategy, seed));
+        // 没有需要保存的记录
+        this.record_saved = true;
+        this
+    }
+
+    /// 从谜题的局面开始，结束时记录是否完成
+    pub fn puzzle(puzzle: Puzzle) -> Self {
+        let mut this = Self::with_game(puzzle.start());
+        this.puzzle = Some(puzzle);
+        this
+    }
 
-        this.animations.push(start_up(&mut this.cells));
+    /// 从沙盒里摆出的局面开始，成绩不会计入玩家的记录
+    pub fn sandbox(cells: Grid, seed: u64) -> Self {
+        let config = GameConfig::with_board_size(cells.len());
+        let mut this = Self::with_game(Game::from_cells(config, seed, cells.clone()));
+        this.sandbox = Some(cells);
+        this.record_saved = true;
         this
     }
 
+    /// 用指定的种子重新开始一局，谜题和沙盒总是回到原来的局面
+    fn restart(&mut self, seed: u64) {
+        // 上一局的存档可能还没删除
+        let pending_save = self.pending_save.take();
+        *self = if let Some(ref autoplay) = self.autoplay {
+            Self::autoplay(self.game.config().clone(), seed, autoplay.index)
+        } else if let Some(puzzle) = self.puzzle.take() {
+            Self::puzzle(puzzle)
+        } else if let Some(cells) = self.sandbox.take() {
+            Self::sandbox(cells, seed)
+        } else {
+            Self::new(self.game.config().clone(), seed)
+        };
+        self.pending_save = pending_save;
+        let mut time = TIME.write().unwrap();
+        time.startup = Instant::now();
+        time.last_update = None;
+    }
+
+    fn seed_update_input(&mut self, event: Event) {
+        let Some(ref mut textarea) = self.seed_input else {
+            return;
+        };
+        let event::Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match key.code {
+            KeyCode::Esc => self.seed_input = None,
+            KeyCode::Enter => {
+                if let Some(seed) = parse_seed(&textarea.lines()[0]) {
+                    self.restart(seed);
+                } else {
+                    let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                    dialog_manager.push(Dialog::new(
+                        " 无效的种子 ",
+                        "种子应为不超过 16 位的十六进制数",
+                        Alignment::Left,
+                        false,
+                        vec![String::from("确定")],
+                        None,
+                    ));
+                }
+            }
+            _ => {
+                textarea.input(key);
+            }
+        }
+    }
+
     fn gameplay_update_input(&mut self, event: Event) {
+        if let Event::Mouse(mouse) = event {
+            self.gameplay_update_mouse(mouse);
+            return;
+        }
         let event::Event::Key(key) = event else {
             return;
         };
         if key.kind != KeyEventKind::Press {
             return;
         }
-        if matches!(key.code, KeyCode::Char('q')) || matches!(key.code, KeyCode::Esc) {
-            self.should_exit = true;
-            self.record_saved = true;
+        let action = keymap::action(Screen::Gameplay, &key);
+        if action == Some(Action::Quit) {
+            self.quit();
+        }
+        if self.game.is_over() {
+            return;
+        }
+        if let Some(ref mut autoplay) = self.autoplay {
+            match action {
+                Some(Action::Pause) => autoplay.paused = !autoplay.paused,
+                Some(Action::Up) => {
+                    autoplay.interval = (autoplay.interval + 1).min(AUTOPLAY_INTERVALS.len() - 1)
+                }
+                Some(Action::Down) => autoplay.interval = autoplay.interval.saturating_sub(1),
+                _ => (),
+            }
+            return;
         }
-        if self.game_over {
+        if action == Some(Action::Pause) {
+            self.pause();
+            return;
+        }
+        // 每日挑战和谜题的种子是固定的
+        if !self.play_started
+            && action == Some(Action::Seed)
+            && !matches!(self.mode(), GameMode::Daily | GameMode::Puzzle)
+        {
+            let mut textarea = TextArea::default();
+            textarea.set_block(Block::bordered().title(" 种子 "));
+            textarea.set_placeholder_text("请输入十六进制种子");
+            self.seed_input = Some(textarea);
             return;
         }
 
-        let mut pressed = false;
-        let mut total_score = 0;
-        let mut animations = Vec::new();
-        match key.code {
-            KeyCode::Up => {
-                let (anim, score) = move_up(&mut self.cells);
-                animations.extend(anim);
-                total_score += score as i32;
-                pressed = true;
+        let direction = match action {
+            Some(Action::Hint) => {
+                self.request_hint();
+                return;
             }
-            KeyCode::Down => {
-                let (anim, score) = move_down(&mut self.cells);
-                animations.extend(anim);
-                total_score += score as i32;
-                pressed = true;
+            Some(Action::Undo) => {
+                if self.game.undo() {
+                    self.hint = HintState::Idle;
+                    self.board.snap(self.game.cells());
+                    self.replay.push(ReplayAction::Undo, self.play_time);
+                }
+                return;
             }
-            KeyCode::Left => {
-                let (anim, score) = move_left(&mut self.cells);
-                animations.extend(anim);
-                total_score += score as i32;
-                pressed = true;
+            Some(Action::Redo) => {
+                if self.game.redo() {
+                    self.hint = HintState::Idle;
+                    self.board.snap(self.game.cells());
+                    self.replay.push(ReplayAction::Redo, self.play_time);
+                }
+                return;
             }
-            KeyCode::Right => {
-                let (anim, score) = move_right(&mut self.cells);
-                animations.extend(anim);
-                total_score += score as i32;
-                pressed = true;
+            Some(Action::Up) => CellMotionDirection::Up,
+            Some(Action::Down) => CellMotionDirection::Down,
+            Some(Action::Left) => CellMotionDirection::Left,
+            Some(Action::Right) => CellMotionDirection::Right,
+            _ => return,
+        };
+        self.apply_direction(direction);
+    }
+
+    /// 退出本局，没有结束的对局留到下次继续
+    fn quit(&mut self) {
+        self.should_exit = true;
+        self.record_saved = true;
+        if self.mode() == GameMode::Daily && self.play_started && !self.game.is_over() {
+            // 每日挑战只有一次机会，中途退出按当前成绩提交
+            self.game.finish();
+            self.dead_dialog = true;
+            self.dead_time = get_time_millis();
+            self.save_replay();
+            self.record_saved = false;
+        } else if self.play_started && self.is_regular() && !self.game.is_over() {
+            let saved = SavedGame {
+                game: self.game.clone(),
+                play_time: self.play_time,
+                replay: self.replay.clone(),
+            };
+            self.pending_save = Some(SaveAction::Write(saved.encode()));
+        }
+    }
+
+    fn pause(&mut self) {
+        if !self.play_started {
+            return;
+        }
+        self.paused = true;
+        self.drag_start = None;
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            " 暂停 ",
+            &format!(
+                "游戏已暂停，计时停止\n\n当前成绩: {} 分\n当前用时: {}秒",
+                self.game.score(),
+                self.play_time.as_secs(),
+            ),
+            Alignment::Center,
+            false,
+            // 每日挑战只有一次机会，不能重新开始
+            if self.mode() == GameMode::Daily {
+                vec![String::from("退出"), String::from("继续游戏")]
+            } else {
+                vec![
+                    String::from("退出"),
+                    String::from("重新开始"),
+                    String::from("继续游戏"),
+                ]
+            },
+            Some(self.pause_dialog_chose.clone()),
+        ));
+    }
+
+    fn update_pause_chose(&mut self) {
+        let chose = self
+            .pause_dialog_chose
+            .load(std::sync::atomic::Ordering::Relaxed);
+        let chose = if self.mode() == GameMode::Daily && chose == 1 {
+            2
+        } else {
+            chose
+        };
+        if chose >= 0 {
+            self.paused = false;
+        }
+        if chose == 0 {
+            self.quit();
+        } else if chose == 1 {
+            // 放弃从存档继续的这一局
+            if self.resumed {
+                self.pending_save = Some(SaveAction::Clear);
+            }
+            self.restart(random_seed());
+        }
+        self.pause_dialog_chose
+            .store(-1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// 在棋盘上拖动鼠标即可移动，每次按下只滑动一次
+    fn gameplay_update_mouse(&mut self, mouse: MouseEvent) {
+        if self.game.is_over() || self.autoplay.is_some() {
+            self.drag_start = None;
+            return;
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = Rect::new(mouse.column, mouse.row, 1, 1);
+                self.drag_start = self
+                    .board_area
+                    .intersects(position)
+                    .then_some((mouse.column, mouse.row));
             }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(start) = self.drag_start else {
+                    return;
+                };
+                if let Some(direction) = swipe_direction(start, (mouse.column, mouse.row)) {
+                    self.drag_start = None;
+                    self.apply_direction(direction);
+                }
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.drag_start = None,
             _ => (),
         }
-        if pressed {
-            if !animations.is_empty() {
-                // 如果地块改变过
-                add_cell(&mut self.cells)
-                    .iter()
-                    .for_each(|x| animations.push(*x));
+    }
+
+    fn apply_direction(&mut self, direction: CellMotionDirection) {
+        let outcome = self.game.apply(direction);
+        if outcome.moved {
+            // 如果地块改变过
+            self.hint = HintState::Idle;
+            self.replay
+                .push(ReplayAction::Move(direction), self.play_time);
+        }
+        self.play_started = true;
+        self.board.animate(outcome.animations);
+        let game_over = outcome.game_over || self.check_puzzle();
+        // 谜题完成后直接结算，不再询问是否继续
+        if self.puzzle.is_some() {
+            if game_over {
+                self.dead_dialog_time = self.app_time + Duration::from_secs(1);
             }
-            animations.sort_by(|a, b| a.animation_type.partial_cmp(&b.animation_type).unwrap());
-            self.play_started = true;
-            self.animations = animations;
-            self.score += total_score;
-            self.game_over = check_game_over(&mut self.cells);
+            return;
+        }
+        // 机器人不会停下，直接继续；同时无路可走时只弹出结算
+        if outcome.won && !outcome.game_over && self.autoplay.is_none() {
+            self.win_dialog_time = Some(self.app_time + Duration::from_millis(500));
         }
-        if self.game_over {
+        if outcome.game_over {
             self.dead_dialog_time = self.app_time + Duration::from_secs(2);
         }
     }
 
-    fn gen_block(itoa_buffer: &mut itoa::Buffer, value: u16, rect: Rect) -> Paragraph {
-        let block_text = if value == 0 {
-            " "
-        } else {
-            itoa_buffer.format(value)
+    /// 谜题完成或者步数用完时结束本局
+    fn check_puzzle(&mut self) -> bool {
+        let Some(ref puzzle) = self.puzzle else {
+            return false;
         };
-        Paragraph::new(block_text)
-            .style(Style::default().fg(colors::color_setter(value)))
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_type(BorderType::Rounded)
-                    .padding(Padding::new(0, 1, rect.height / 3, 1)),
+        if self.game.is_over() {
+            return true;
+        }
+        if puzzle.is_solved(&self.game) || puzzle.out_of_moves(&self.game) {
+            self.game.finish();
+            return true;
+        }
+        false
+    }
+
+    /// 普通的对局，机器人、谜题和沙盒都不能中途保存
+    fn is_regular(&self) -> bool {
+        self.autoplay.is_none() && self.puzzle.is_none() && self.sandbox.is_none()
+    }
+
+    /// 本局的成绩是否进入排行榜
+    fn is_ranked(&self) -> bool {
+        self.autoplay.is_none() && self.mode() != GameMode::Puzzle && self.sandbox.is_none()
+    }
+
+    /// 本局是否达成了目标
+    fn solved(&self) -> bool {
+        match self.puzzle {
+            Some(ref puzzle) => puzzle.is_solved(&self.game),
+            None => self.game.has_won(),
+        }
+    }
+
+    fn update_autoplay(&mut self, delta: Duration) {
+        let Some(ref mut autoplay) = self.autoplay else {
+            return;
+        };
+        if self.game.is_over() {
+            return;
+        }
+
+        if let Some(ref rx) = autoplay.thinking {
+            let direction = match rx.try_recv() {
+                Ok((strategy, direction)) => {
+                    autoplay.strategy = Some(strategy);
+                    direction
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                // 策略在线程里崩溃了，换一个新的继续，种子由局面决定，重放时仍然一致
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    let moves = self.game.stats().total_moves() as u64;
+                    let seed = derive_seed(self.game.seed(), moves);
+                    autoplay.strategy = Some(STRATEGIES[autoplay.index].1(seed));
+                    None
+                }
+            };
+            autoplay.thinking = None;
+            autoplay.wait = Duration::ZERO;
+            if let Some(direction) = direction {
+                self.apply_direction(direction);
+            }
+            return;
+        }
+
+        if autoplay.paused {
+            return;
+        }
+        autoplay.wait += delta;
+        if autoplay.wait < AUTOPLAY_INTERVALS[autoplay.interval] {
+            return;
+        }
+        let Some(mut strategy) = autoplay.strategy.take() else {
+            return;
+        };
+        let (tx, rx) = mpsc::channel();
+        let game = self.game.fork();
+        thread::spawn(move || {
+            let direction = strategy.choose(&game);
+            let _ = tx.send((strategy, direction));
+        });
+        autoplay.thinking = Some(rx);
+    }
+
+    fn request_hint(&mut self) {
+        if !matches!(self.hint, HintState::Idle) {
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let cells = self.game.cells().clone();
+        let spawn = self.game.config().spawn.clone();
+        thread::spawn(move || {
+            // 局面改变后接收端已被丢弃，结果直接作废
+            let _ = tx.send(ai::hint(&cells, spawn.as_ref()));
+        });
+        self.hint = HintState::Searching(rx);
+    }
+
+    fn update_hint(&mut self) {
+        let HintState::Searching(ref rx) = self.hint else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Some(hint)) => self.hint = HintState::Ready(hint),
+            Ok(None) | Err(mpsc::TryRecvError::Disconnected) => self.hint = HintState::NoMove,
+            Err(mpsc::TryRecvError::Empty) => (),
+        }
+    }
+
+    /// 按当前的按键配置写出操作说明
+    fn controls_text(&self) -> String {
+        let key = |action| keymap::label(Screen::Gameplay, action);
+        if self.autoplay.is_some() {
+            format!(
+                "{} 暂停\n{} {} 调整速度",
+                key(Action::Pause),
+                key(Action::Up),
+                key(Action::Down)
+            )
+        } else {
+            format!(
+                "{} {} {} {} 或拖动棋盘\n{} 撤销 | {} 重做 | {} 提示\n{} 暂停",
+                key(Action::Left),
+                key(Action::Up),
+                key(Action::Down),
+                key(Action::Right),
+                key(Action::Undo),
+                key(Action::Redo),
+                key(Action::Hint),
+                key(Action::Pause)
             )
-            .alignment(Alignment::Center)
+        }
+    }
+
+    fn hint_text(&self) -> String {
+        if let Some(ref autoplay) = self.autoplay {
+            return format!(
+                "{} | 间隔 {}ms{}",
+                autoplay.name(),
+                AUTOPLAY_INTERVALS[autoplay.interval].as_millis(),
+                if autoplay.paused { " | 已暂停" } else { "" }
+            );
+        }
+        match self.hint {
+            HintState::Idle => format!("按 {} 获取", keymap::label(Screen::Gameplay, Action::Hint)),
+            HintState::Searching(_) => String::from("思考中…"),
+            HintState::Ready(hint) => format!(
+                "{} 评估 {:.1}k | 领先 {:.0}%",
+                direction_arrow(hint.direction),
+                hint.evaluation / 1000.0,
+                hint.margin * 100.0
+            ),
+            HintState::NoMove => String::from("无路可走"),
+        }
+    }
+
+    /// 在棋盘中央画出建议的方向
+    fn draw_hint(&self, frame: &mut Frame<'_>, board: Rect) {
+        let HintState::Ready(hint) = self.hint else {
+            return;
+        };
+        let [_, area, _] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Length(7),
+            Constraint::Fill(1),
+        ])
+        .areas(board);
+        let [_, area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(direction_arrow(hint.direction))
+                .block(Block::bordered().border_type(BorderType::Double))
+                .fg(tailwind::AMBER.c300)
+                .alignment(Alignment::Center),
+            area,
+        );
+    }
+
+    pub fn board_size(&self) -> usize {
+        self.game.board_size()
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.game.config().mode
+    }
+
+    /// 限时模式剩余的时间
+    fn time_left(&self) -> Option<Duration> {
+        self.mode()
+            .time_limit()
+            .map(|x| x.saturating_sub(self.play_time))
+    }
+
+    fn is_time_up(&self) -> bool {
+        self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// 限时模式进入最后几秒
+    fn in_final_seconds(&self) -> bool {
+        !self.game.is_over()
+            && self.play_started
+            && self.time_left().is_some_and(|x| x <= TIME_ATTACK_WARNING)
+    }
+
+    fn time_text(&self) -> String {
+        match self.time_left() {
+            Some(left) => {
+                // 向上取整，归零时正好结束
+                let secs = left.as_millis().div_ceil(1000);
+                format!("⏳ 剩余时间:\n{}:{:02}", secs / 60, secs % 60)
+            }
+            None => format!("⌚ 游玩时间:\n{}", self.play_time.as_secs()),
+        }
+    }
+
+    fn moves_text(&self) -> String {
+        let moves = self.game.stats().total_moves();
+        let limit = match self.puzzle {
+            Some(ref puzzle) => puzzle.move_limit,
+            None => self.game.config().mode.move_limit(),
+        };
+        match limit {
+            Some(limit) => format!("{moves}/{limit}"),
+            None => moves.to_string(),
+        }
+    }
+
+    fn target_text(&self) -> String {
+        if let Some(ref puzzle) = self.puzzle {
+            return if self.solved() {
+                format!("{} ✔", puzzle.goal_text())
+            } else {
+                puzzle.goal_text()
+            };
+        }
+        match self.game.config().target_tile {
+            0 => String::from("无"),
+            target if self.game.has_won() => format!("{target} ✔"),
+            target => target.to_string(),
+        }
     }
 
     #[allow(clippy::needless_range_loop)]
     fn gameplay_draw(&mut self, frame: &mut Frame<'_>) {
-        let exp_out = Interpolation::ExpOut { value: 20.0 };
         let area = frame.area();
 
         let [title, div] = Layout::vertical([Constraint::Max(3), Constraint::Min(0)]).areas(area);
@@ -165,32 +756,40 @@ impl GameplayActivity {
         .flex(Flex::Center)
         .split(div);
 
-        let outer_lower = Layout::vertical(vec![
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-            Constraint::Percentage(25),
-        ])
-        .split(outer_subdiv[1]);
-
-        let mut cols: [Rc<[Rect]>; 4] = [Rc::new([]), Rc::new([]), Rc::new([]), Rc::new([])];
-
-        for i in 0..4 {
-            cols[i] = Layout::horizontal(vec![
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-                Constraint::Percentage(25),
-            ])
-            .split(outer_lower[i]);
-        }
+        let size = self.board_size();
 
         // 内容绘制
 
-        let text = if self.play_started {
-            "2048 小游戏"
+        let puzzle_title;
+        let start_hint;
+        let text = if self.autoplay.is_some() {
+            "2048 小游戏 | 机器人演示"
+        } else if let Some(ref puzzle) = self.puzzle {
+            puzzle_title = format!("谜题: {} | 目标: {}", puzzle.name, puzzle.goal_text());
+            puzzle_title.as_str()
+        } else if self.sandbox.is_some() && self.play_started {
+            "2048 小游戏 | 沙盒"
+        } else if self.play_started {
+            match self.mode() {
+                GameMode::Classic => "2048 小游戏",
+                GameMode::TimeAttack => "2048 小游戏 | 限时模式",
+                GameMode::LimitedMoves => "2048 小游戏 | 限步模式",
+                GameMode::Daily => "2048 小游戏 | 每日挑战",
+                GameMode::Puzzle => "2048 小游戏 | 谜题",
+            }
+        } else if self.mode() == GameMode::Daily {
+            "按方向键以开始今天的挑战 | 只有一次机会"
         } else {
-            "按方向键以开始游戏"
+            start_hint = format!(
+                "按方向键以开始{} | 按 {} 输入种子",
+                if self.mode().time_limit().is_some() {
+                    "计时"
+                } else {
+                    "游戏"
+                },
+                keymap::label(Screen::Gameplay, Action::Seed)
+            );
+            start_hint.as_str()
         };
         let header = Paragraph::new(text)
             .style(Style::default().fg(tailwind::GREEN.c50))
@@ -203,99 +802,33 @@ impl GameplayActivity {
             .alignment(Alignment::Center);
         frame.render_widget(header, title);
 
-        {
-            let cell = Paragraph::default()
-                .style(Style::default().fg(colors::color_setter(0)))
+        self.board_area = outer_subdiv[1];
+        if self.paused {
+            // 暂停时不能偷看棋盘
+            let cover = Paragraph::new("⏸ 已暂停")
+                .fg(tailwind::INDIGO.c300)
                 .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .border_type(BorderType::Rounded),
-                );
-            for rows in &cols {
-                for row in rows.iter() {
-                    frame.render_widget(&cell, *row);
-                }
-            }
-        }
-
-        // Popup和Move动画完成后，将cells复制到visual_cells
-        self.animations.retain(|x| {
-            (matches!(x.animation_type, CellAnimationType::Popup) && x.duration.as_secs_f32() < 0.3)
-                || (matches!(x.animation_type, CellAnimationType::Move)
-                    && x.duration.as_secs_f32() < 0.2)
-        });
-
-        // 从显示列表删除地块
-        for cell in &self.animations {
-            self.visual_cells[cell.src.x][cell.src.y].set_v(0);
-        }
-        // 播放动画
-        for cell in &self.animations {
-            match cell.animation_type {
-                CellAnimationType::Popup => {
-                    let progress = inverse_lerp(0.0..=0.8_f32, cell.duration.as_secs_f32());
-                    let rect = rect_scale(cols[cell.src.x][cell.src.y], exp_out.apply(progress));
-                    frame.render_widget(Clear, rect);
-                    frame.render_widget(
-                        Self::gen_block(&mut self.itoa_buffer, cell.value, rect),
-                        rect,
-                    );
-                }
-                CellAnimationType::Move => {
-                    let progress = inverse_lerp(0.0..=0.6_f32, cell.duration.as_secs_f32());
-                    let dest = cell.dest.as_ref().unwrap();
-                    let rect = rect_move(
-                        cols[cell.src.x][cell.src.y],
-                        // TODO: 这里似乎有个bug，但是暂时无法复现
-                        cols[dest.x][dest.y],
-                        exp_out.apply(progress),
-                    );
-                    frame.render_widget(Clear, rect);
-                    frame.render_widget(
-                        Self::gen_block(&mut self.itoa_buffer, cell.value, rect),
-                        rect,
-                    );
-                }
-            }
-        }
-        // 动画播放完成后，把判断列表里的地块全部复制到显示列表
-        for i in 0..4 {
-            for j in 0..4 {
-                let value = self.cells[i][j].get();
-                if !self.animations.iter().any(|x| {
-                    if matches!(x.animation_type, CellAnimationType::Move) {
-                        let dest = x.dest.unwrap();
-                        dest.x == i && dest.y == j
-                    } else {
-                        x.src.x == i && x.src.y == j
-                    }
-                }) {
-                    self.visual_cells[i][j].set_v(value);
-                }
-            }
-        }
-
-        {
-            let time = TIME.read().unwrap();
-            for cell in &mut self.animations {
-                cell.duration += time.delta;
-            }
+                    Block::bordered()
+                        .border_type(BorderType::Rounded)
+                        .padding(Padding::top(outer_subdiv[1].height.saturating_sub(3) / 2)),
+                )
+                .alignment(Alignment::Center);
+            frame.render_widget(cover, outer_subdiv[1]);
+        } else {
+            self.board.draw(frame, outer_subdiv[1], self.game.cells());
+            self.draw_hint(frame, outer_subdiv[1]);
         }
 
-        for i in 0..4 {
-            for j in 0..4 {
-                let value = self.visual_cells[i][j].get();
-                if value == 0 {
-                    continue;
-                }
-                frame.render_widget(
-                    Self::gen_block(&mut self.itoa_buffer, value, cols[i][j]),
-                    cols[i][j],
-                );
-            }
-        }
+        self.show_score =
+            lerpf(self.show_score as f32..=self.game.score() as f32, 0.1).round() as u64;
 
-        self.show_score = lerpf(self.show_score as f32..=self.score as f32, 0.1).round() as i32;
+        // 最后几秒面板边框每秒闪烁两次
+        let warning = self.in_final_seconds();
+        let stats_border = if warning && self.app_time.subsec_millis() < 500 {
+            tailwind::RED.c500
+        } else {
+            tailwind::INDIGO.c300
+        };
 
         let stats_detail = Paragraph::new(format!(
             indoc::indoc! {"
@@ -305,27 +838,50 @@ impl GameplayActivity {
 
                 分数: {:04}
                 最佳: {:04}
+                棋盘: {}×{}
+                目标: {}
+                步数: {}
 
-                ⌚ 游玩时间:
                 {}
 
+                🎲 种子:
+                {}
 
+                ↶ 剩余撤销: {}
 
+                {}
+                {}
 
                 🎮 如何控制:
-                ← ↑ ↓ →
-
+                {}
 
-                键入'Q'以退出游戏
+                键入'{}'以退出游戏
             "},
             self.high_score.name,
             self.show_score,
             self.high_score.best_score,
-            self.play_time.as_secs()
+            size,
+            size,
+            self.target_text(),
+            self.moves_text(),
+            self.time_text(),
+            format_seed(self.game.seed()),
+            self.game
+                .undo_remaining()
+                .map_or(String::from("不限"), |x| x.to_string()),
+            if self.autoplay.is_some() {
+                "🤖 机器人:"
+            } else {
+                "💡 AI 提示:"
+            },
+            self.hint_text(),
+            self.controls_text(),
+            keymap::label(Screen::Gameplay, Action::Quit),
         ))
         .style(Style::default().fg(tailwind::INDIGO.c300))
         .block(
             Block::default()
+                .border_style(Style::default().fg(stats_border))
                 .title("Stats")
                 .title_alignment(Alignment::Left)
                 .borders(Borders::ALL)
@@ -336,17 +892,92 @@ impl GameplayActivity {
         .alignment(Alignment::Left);
         frame.render_widget(stats_detail, outer_subdiv[2]);
 
-        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[0]);
-        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[3]);
+        if warning {
+            frame.render_widget(fx::gen_alarm(self.app_time), outer_subdiv[0]);
+            frame.render_widget(fx::gen_alarm(self.app_time), outer_subdiv[3]);
+        } else {
+            frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[0]);
+            frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[3]);
+        }
+
+        self.draw_seed_input(frame);
 
         fade_in(frame, 0.8, self.app_time.as_secs_f32(), None);
     }
 
+    fn draw_seed_input(&self, frame: &mut Frame<'_>) {
+        let Some(ref textarea) = self.seed_input else {
+            return;
+        };
+
+        let buf = frame.buffer_mut();
+        buf.content.iter_mut().for_each(|x| {
+            if let Color::Rgb(r, g, b) = x.fg {
+                x.fg = Color::Rgb(
+                    r.saturating_sub(100),
+                    g.saturating_sub(100),
+                    b.saturating_sub(100),
+                );
+            }
+        });
+
+        let [_, chunk, _] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Percentage(40),
+            Constraint::Fill(1),
+        ])
+        .areas(frame.area());
+        let [_, dialog, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ])
+        .areas(chunk);
+
+        frame.render_widget(Clear, dialog);
+        let block = Block::bordered()
+            .border_type(BorderType::Double)
+            .title_bottom("( ⏎ ) 确定 | ( ESC ) 取消")
+            .title_alignment(Alignment::Right)
+            .fg(tailwind::WHITE);
+        frame.render_widget(&block, dialog);
+        frame.render_widget(textarea, block.inner(dialog));
+    }
+
+    fn queue_win_message(&mut self) {
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            " 胜利 ",
+            &format!(
+                "{}\n成功合成了 {}！\n\n当前成绩: {} 分\n当前用时: {}秒\n\n继续游戏可以冲击更高的分数",
+                ascii::you_win(),
+                self.game.config().target_tile,
+                self.game.score(),
+                self.play_time.as_secs(),
+            ),
+            Alignment::Center,
+            false,
+            vec![String::from("继续游戏"), String::from("结束本局")],
+            Some(self.win_dialog_chose.clone()),
+        ));
+    }
+
+    fn update_win_chose(&mut self) {
+        let chose = self
+            .win_dialog_chose
+            .load(std::sync::atomic::Ordering::Relaxed);
+        if chose == 1 {
+            self.game.finish();
+            self.dead_dialog_time = self.app_time;
+        }
+        self.win_dialog_chose
+            .store(-1, std::sync::atomic::Ordering::Relaxed);
+    }
+
     pub fn queue_clear_message(&mut self) {
         let dialog_chose = self.dead_dialog_chose.clone();
-        let ascii_art = if self.score < self.high_score.best_score {
-            let mut rng = rand::thread_rng();
-            let num = rng.gen_range(1..=10);
+        let ascii_art = if to_record(self.game.score()) < self.high_score.best_score {
+            let num = rand::thread_rng().gen_range(1..=10);
             if num <= 2 {
                 ascii::god_fall()
             } else {
@@ -359,16 +990,41 @@ impl GameplayActivity {
         dialog_manager.push(Dialog::new(
             " 游戏结束 ",
             &format!(
-                "{}\n已经没有块可以移动了！\n\n最终成绩: {} 分\n最高成绩: {} 分 ({:+})\n最终用时: {}秒",
+                "{}\n{}\n\n最终成绩: {} 分\n最高成绩: {} 分 ({:+})\n最大地块: {}\n最终用时: {}秒\n种子: {}",
                 ascii_art,
-                self.score,
+                if self.puzzle.is_some() {
+                    if self.solved() {
+                        String::from("谜题完成！")
+                    } else if self.puzzle.as_ref().is_some_and(|x| x.out_of_moves(&self.game)) {
+                        String::from("步数用完了！")
+                    } else {
+                        String::from("已经没有块可以移动了！")
+                    }
+                } else if self.game.has_won() {
+                    format!("成功合成了 {}！", self.game.config().target_tile)
+                } else if self.is_time_up() {
+                    String::from("时间到！")
+                } else if self.game.moves_left() == Some(0) {
+                    String::from("步数用完了！")
+                } else {
+                    String::from("已经没有块可以移动了！")
+                },
+                self.game.score(),
                 self.high_score.best_score,
-                self.score - self.high_score.best_score,
+                self.game.score() as i128 - self.high_score.best_score as i128,
+                self.game.max_tile().value(),
                 self.play_time.as_secs(),
+                format_seed(self.game.seed()),
             ),
             Alignment::Center,
             false,
-            vec![String::from("重试"), String::from("查看排行"), String::from("退出")],
+            if !self.is_ranked() {
+                vec![String::from("重试"), String::from("退出")]
+            } else if self.mode() == GameMode::Daily {
+                vec![String::from("查看排行"), String::from("退出")]
+            } else {
+                vec![String::from("重试"), String::from("查看排行"), String::from("退出")]
+            },
             Some(dialog_chose.clone()),
         ));
     }
@@ -377,11 +1033,16 @@ impl GameplayActivity {
         let chose = self
             .dead_dialog_chose
             .load(std::sync::atomic::Ordering::Relaxed);
+        // 机器人、谜题和沙盒的成绩不进排行榜，对话框里没有“查看排行”；每日挑战不能重试
+        let chose = if !self.is_ranked() && chose == 1 {
+            2
+        } else if self.mode() == GameMode::Daily && chose >= 0 {
+            chose + 1
+        } else {
+            chose
+        };
         if chose == 0 {
-            *self = Self::new();
-            let mut time = TIME.write().unwrap();
-            time.startup = Instant::now();
-            time.last_update = None;
+            self.restart(random_seed());
         } else if chose == 1 {
             self.show_ranking = true;
         } else if chose == 2 {
@@ -391,14 +1052,122 @@ impl GameplayActivity {
             .store(-1, std::sync::atomic::Ordering::Relaxed);
     }
 
+    fn save_replay(&mut self) {
+        // 录像只能从种子重建开局，还原不出谜题和沙盒的局面
+        if self.puzzle.is_some() || self.sandbox.is_some() {
+            return;
+        }
+        self.replay.score = self.game.score();
+        self.replay.timestamp = self.dead_time;
+        let path = match self.autoplay {
+            Some(ref autoplay) => {
+                self.replay.bot = Some(autoplay.name().to_string());
+                bot_replay_path(self.dead_time)
+            }
+            None => replay_path(self.high_score.id, self.dead_time),
+        };
+        if let Err(e) = self.replay.save(path) {
+            let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+            dialog_manager.push(Dialog::new(
+                " 遇到问题 ",
+                &format!("保存录像失败: {e}"),
+                Alignment::Left,
+                true,
+                vec![String::from("确定")],
+                None,
+            ));
+        }
+    }
+
+    /// 存档还没有写入数据层
+    pub fn is_saving(&self) -> bool {
+        self.pending_save.is_some()
+    }
+
+    fn update_pending_save(&mut self) {
+        if !self.player_requested {
+            return;
+        }
+        let Some(ref action) = self.pending_save else {
+            return;
+        };
+        let game = match action {
+            SaveAction::Write(text) => Some(text.clone()),
+            SaveAction::Clear => None,
+        };
+        // 保存失败时 `data_manager!` 会弹窗提示，不再重试
+        if data_manager!(save_game, self.high_score.clone(), game).is_some() {
+            self.pending_save = None;
+        }
+    }
+
+    /// 查询当前玩家和对应的最高分，成绩和存档都要等查到玩家后才能写入
+    fn update_player(&mut self) {
+        // 不能用默认的玩家代替，否则成绩和存档会记到编号为 0 的玩家名下
+        let player = match data_manager!(get_current_player) {
+            None => return,
+            Some(Ok(player)) => player,
+            Some(Err(e)) if self.should_exit || !e.is_transient() => {
+                // 已经要离开了，或者玩家已不存在，放弃本局的成绩和存档
+                self.record_saved = true;
+                self.pending_save = None;
+                let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                dialog_manager.push(Dialog::new(
+                    " 遇到问题 ",
+                    "无法确认当前玩家，本局的成绩和存档没有保存",
+                    Alignment::Center,
+                    false,
+                    vec![String::from("确定")],
+                    None,
+                ));
+                return;
+            }
+            Some(Err(_)) => {
+                self.player_retry_time = self.app_time + RETRY_INTERVAL;
+                return;
+            }
+        };
+        self.player_requested = true;
+        self.high_score = if let Some(ref puzzle) = self.puzzle {
+            // 谜题只和同一道谜题比较
+            let id = puzzle.id();
+            player.filter_records(|x| x.mode() == GameMode::Puzzle && x.puzzle as u64 == id)
+        } else if self.mode() == GameMode::Daily {
+            // 每日挑战只和当天的成绩比较
+            let seed = self.game.seed();
+            player.filter_records(|x| x.mode() == GameMode::Daily && x.seed as u64 == seed)
+        } else {
+            player.for_leaderboard(self.board_size(), self.mode())
+        };
+    }
+
+    /// 本局的成绩，`records` 中只包含本局这一条记录
     pub fn get_save(&self) -> Player {
+        let stats = self.game.stats();
         Player {
             id: self.high_score.id,
             name: self.high_score.name.to_owned(),
-            best_score: self.score,
+            best_score: to_record(self.game.score()),
             best_time: self.play_time.as_secs() as i64,
             best_timestamp: self.dead_time,
-            records: self.high_score.records.clone(),
+            records: vec![PlayerRecord {
+                score: to_record(self.game.score()),
+                time: self.play_time.as_secs() as i64,
+                timestamp: self.dead_time,
+                board_size: self.board_size() as i32,
+                seed: self.game.seed() as i64,
+                undo_count: self.game.undo_used() as i32,
+                won: self.solved(),
+                max_tile: to_record(self.game.max_tile().value()),
+                moves: stats.total_moves() as i32,
+                merges: stats.merges as i32,
+                moves_up: stats.moves_towards(CellMotionDirection::Up) as i32,
+                moves_down: stats.moves_towards(CellMotionDirection::Down) as i32,
+                moves_left: stats.moves_towards(CellMotionDirection::Left) as i32,
+                moves_right: stats.moves_towards(CellMotionDirection::Right) as i32,
+                mode: self.game.config().mode.id(),
+                puzzle: self.puzzle.as_ref().map_or(0, |x| x.id() as i64),
+            }],
         }
     }
 }
@@ -409,44 +1178,97 @@ impl Activity for GameplayActivity {
     }
 
     fn update(&mut self, event: Option<Event>) {
-        {
-            let time = TIME.read().unwrap();
-            self.app_time += time.delta;
-            if !self.game_over && self.play_started {
-                self.play_time += time.delta;
+        let delta = TIME.read().unwrap().delta;
+        self.app_time += delta;
+        if !self.game.is_over() && self.play_started && !self.paused {
+            self.play_time += delta;
+            if let Some(limit) = self.mode().time_limit()
+                && self.play_time >= limit
+            {
+                // 时间用尽，本局按当前分数结算
+                self.play_time = limit;
+                self.game.finish();
+                self.win_dialog_time = None;
+                self.dead_dialog_time = self.app_time + Duration::from_secs(1);
             }
         }
 
-        if !self.player_requested {
-            if let Some(player) = data_manager!(get_current_player) {
-                self.player_requested = true;
-                self.high_score = player;
-            }
+        if !self.player_requested && (self.should_exit || self.app_time >= self.player_retry_time) {
+            self.update_player();
         }
 
+        self.update_hint();
+        self.update_autoplay(delta);
+
         if let Some(event) = event {
-            self.gameplay_update_input(event);
+            if self.seed_input.is_some() {
+                self.seed_update_input(event);
+            } else {
+                self.gameplay_update_input(event);
+            }
         }
 
-        if self.should_exit && self.game_over && !self.record_saved {
-            let mut player = self.get_save();
-            player.records = vec![PlayerRecord {
-                score: player.best_score,
-                time: player.best_time,
-                timestamp: player.best_timestamp,
-            }];
-            if let Some(_) = data_manager!(save_record, player) {
+        if self.should_exit && self.game.is_over() && self.player_requested && !self.record_saved {
+            let player = self.get_save();
+            if data_manager!(save_record, player).is_some() {
                 self.record_saved = true;
             };
         }
 
-        if self.game_over {
+        self.update_pending_save();
+
+        if let Some(time) = self.win_dialog_time
+            && self.app_time > time
+        {
+            self.win_dialog_time = None;
+            self.queue_win_message();
+        }
+        self.update_win_chose();
+        self.update_pause_chose();
+
+        if self.game.is_over() {
             if !self.dead_dialog && self.app_time > self.dead_dialog_time {
                 self.dead_dialog = true;
                 self.dead_time = get_time_millis();
+                if self.resumed {
+                    self.resumed = false;
+                    self.pending_save = Some(SaveAction::Clear);
+                }
+                // 先写入录像，结算后的排行榜里就能回放本局
+                self.save_replay();
                 self.queue_clear_message();
             }
             self.update_clear_chose();
         }
     }
 }
+
+/// 拖动的方向，距离不够时返回 `None`
+fn swipe_direction(from: (u16, u16), to: (u16, u16)) -> Option<CellMotionDirection> {
+    // 终端的字符大约是两列一行见方
+    let dx = (to.0 as i32 - from.0 as i32) / 2;
+    let dy = to.1 as i32 - from.1 as i32;
+    if dx.abs().max(dy.abs()) < SWIPE_THRESHOLD {
+        return None;
+    }
+    Some(if dx.abs() > dy.abs() {
+        if dx > 0 {
+            CellMotionDirection::Right
+        } else {
+            CellMotionDirection::Left
+        }
+    } else if dy > 0 {
+        CellMotionDirection::Down
+    } else {
+        CellMotionDirection::Up
+    })
+}
+
+fn direction_arrow(direction: CellMotionDirection) -> &'static str {
+    match direction {
+        CellMotionDirection::Up => "↑",
+        CellMotionDirection::Down => "↓",
+        CellMotionDirection::Left => "←",
+        CellMotionDirection::Right => "→",
+    }
+}
@@ -1,11 +1,12 @@
 use std::{
     io::Cursor,
+    path::PathBuf,
     sync::{Arc, atomic::AtomicI8},
     time::Duration,
 };
 
 use chrono::TimeZone;
-use crossterm::event::{Event, KeyCode, KeyEventKind};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
 use image::{AnimationDecoder, DynamicImage, codecs::gif::GifDecoder};
 use rand::Rng;
 use ratatui::{
@@ -26,9 +27,15 @@ use tui_textarea::TextArea;
 
 use crate::{
     app::{
+        gameplay::{
+            format_seed,
+            replay::{Replay, ReplayPlayer, replay_path},
+            to_record,
+        },
+        keymap::{self, Action, Screen},
         manage::PlayerListSelector,
         math::inverse_lerp_f64,
-        structs::Player,
+        structs::{Player, PlayerRecord},
         time::TIME,
         utils::{fade_in, format_date_short, format_datetime},
     },
@@ -45,6 +52,8 @@ static DORO: &[u8] = include_bytes!("../manage/doro.gif");
 
 pub struct ManageActivity<'a> {
     pub should_exit: bool,
+    /// 选中记录的录像，等待打开
+    pub open_replay: Option<PathBuf>,
     selector: PlayerListSelector<'a>,
     in_selector: bool,
     player: Player,
@@ -62,6 +71,8 @@ pub struct ManageActivity<'a> {
     record_state: TableState,
     record_scroll: ScrollbarState,
     record_remove_entered: bool,
+    /// 已经从列表里删除、还没有写入数据层的记录
+    removed_records: Vec<PlayerRecord>,
 
     chart_earliest: i64,
     chart_latest: i64,
@@ -97,6 +108,7 @@ impl ManageActivity<'_> {
             selector: PlayerListSelector::new("玩家管理"),
             in_selector: true,
             should_exit: false,
+            open_replay: None,
             player: Player::default(),
             self_id,
             bg_time: Duration::default(),
@@ -110,6 +122,7 @@ impl ManageActivity<'_> {
             record_scroll: ScrollbarState::default(),
             record_state: TableState::default(),
             record_remove_entered: false,
+            removed_records: Vec::new(),
             chart_earliest: 0,
             chart_latest: 0,
             chart_max: 0.0,
@@ -212,16 +225,31 @@ impl ManageActivity<'_> {
     fn draw_table(&mut self, area: Rect, frame: &mut Frame<'_>) {
         let widths = [
             Constraint::Fill(1),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(6),
             Constraint::Fill(1),
             Constraint::Fill(1),
+            Constraint::Length(17),
             Constraint::Length(8),
         ];
-        let header = ["分数", "所用时间", "达成时间", "操作"]
-            .into_iter()
-            .map(Cell::from)
-            .collect::<Row>()
-            .bg(tailwind::BLUE.c500)
-            .fg(tailwind::BLUE.c50);
+        let header = [
+            "分数",
+            "模式",
+            "棋盘",
+            "最大地块",
+            "步数",
+            "所用时间",
+            "达成时间",
+            "种子",
+            "操作",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .bg(tailwind::BLUE.c500)
+        .fg(tailwind::BLUE.c50);
 
         let table = Table::new(self.record_rows.clone(), widths)
             .header(header)
@@ -329,7 +357,20 @@ impl ManageActivity<'_> {
     }
 
     fn draw_hint(&self, area: Rect, frame: &mut Frame<'_>) {
-        let para = Paragraph::new("( ← ↑ ↓ → ) 移动光标 | ( S ) 返回选择界面 | ( ESC ) 退出").block(
+        let key = |action| keymap::label(Screen::Manage, action);
+        let para = Paragraph::new(format!(
+            "( {} {} {} {} ) 移动光标 | ( {} ) 播放录像 | ( {} ) 校验录像 | ( {} ) 对局详情 | ( {} ) 返回选择界面 | ( {} ) 退出",
+            key(Action::Left),
+            key(Action::Up),
+            key(Action::Down),
+            key(Action::Right),
+            key(Action::Replay),
+            key(Action::Verify),
+            key(Action::Info),
+            key(Action::Switch),
+            key(Action::Quit)
+        ))
+        .block(
             Block::bordered()
                 .border_type(BorderType::Rounded)
                 .fg(tailwind::YELLOW.c400),
@@ -338,26 +379,32 @@ impl ManageActivity<'_> {
     }
 
     fn draw_remove(&self, area: Rect, frame: &mut Frame<'_>) {
-        let para = Paragraph::new("键入 D 彻底删除玩家")
-            .fg(tailwind::RED.c50)
-            .block(
-                Block::bordered()
-                    .border_type(BorderType::Double)
-                    .fg(tailwind::RED.c600),
-            )
-            .alignment(Alignment::Center);
+        let para = Paragraph::new(format!(
+            "键入 {} 彻底删除玩家",
+            keymap::label(Screen::Manage, Action::Delete)
+        ))
+        .fg(tailwind::RED.c50)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Double)
+                .fg(tailwind::RED.c600),
+        )
+        .alignment(Alignment::Center);
         frame.render_widget(para, area);
     }
 
     fn draw_rename(&self, area: Rect, frame: &mut Frame<'_>) {
-        let para = Paragraph::new("键入 R 更改名称")
-            .fg(tailwind::LIME.c50)
-            .alignment(Alignment::Center)
-            .block(
-                Block::bordered()
-                    .fg(tailwind::LIME.c400)
-                    .border_type(BorderType::Rounded),
-            );
+        let para = Paragraph::new(format!(
+            "键入 {} 更改名称",
+            keymap::label(Screen::Manage, Action::Rename)
+        ))
+        .fg(tailwind::LIME.c50)
+        .alignment(Alignment::Center)
+        .block(
+            Block::bordered()
+                .fg(tailwind::LIME.c400)
+                .border_type(BorderType::Rounded),
+        );
         frame.render_widget(para, area);
     }
 
@@ -371,8 +418,17 @@ impl ManageActivity<'_> {
             .map(|x| {
                 [
                     Cell::from(buffer.format(x.score).to_string()),
+                    Cell::from(x.mode().name()),
+                    Cell::from(format!("{}×{}", x.board_size(), x.board_size())),
+                    Cell::from(match x.max_tile {
+                        0 => String::from("-"),
+                        tile if x.won => format!("{tile} ✔"),
+                        tile => tile.to_string(),
+                    }),
+                    Cell::from(buffer.format(x.moves).to_string()),
                     Cell::from(buffer.format(x.time).to_string()),
                     Cell::from(format_datetime(x.timestamp)),
+                    Cell::from(format_seed(x.seed as u64)),
                     Cell::from("删除"),
                 ]
                 .into_iter()
@@ -382,52 +438,60 @@ impl ManageActivity<'_> {
     }
 
     fn update_data(&mut self) {
-        if self.update_required {
-            if let Some(result) = data_manager!(update_player, self.player.clone()) {
-                if !result {
-                    let mut dialog_manger = DIALOG_MANAGER.write().unwrap();
-                    dialog_manger.push(Dialog::new(
-                        " 错误 ",
-                        "修改失败：在更新内置数据库时遇到问题",
-                        Alignment::Left,
-                        false,
-                        vec![String::from("确定")],
-                        None,
-                    ));
-                }
-                self.update_required = false;
+        // 失败的原因由 `data_manager!` 弹窗提示
+        if self.update_required && data_manager!(update_player, self.player.clone()).is_some() {
+            self.update_required = false;
+        }
+        if !self.removed_records.is_empty() {
+            let player = Player {
+                records: self.removed_records.clone(),
+                ..self.player.clone()
+            };
+            let sent = player.records.len();
+            if data_manager!(remove_record, player).is_some() {
+                self.removed_records.drain(..sent);
             }
         }
-        if self.remove_required {
-            if let Some(result) = data_manager!(remove_player, self.player.clone()) {
-                if !result {
-                    let mut dialog_manger = DIALOG_MANAGER.write().unwrap();
-                    dialog_manger.push(Dialog::new(
-                        " 错误 ",
-                        "删除失败：在更新内置数据库时遇到问题",
-                        Alignment::Left,
-                        false,
-                        vec![String::from("确定")],
-                        None,
-                    ));
-                } else {
-                    self.should_exit = true;
-                }
+        if self.remove_required
+            && let Some(result) = data_manager!(remove_player, self.player.clone())
+        {
+            self.remove_required = false;
+            if result.is_ok() {
+                self.should_exit = true;
             }
         }
     }
 
-    fn update_input(&mut self, key: KeyCode) -> bool {
-        let disable_flag = self.renaming || self.record_remove_entered;
-        match key {
-            KeyCode::Char('s') => {
-                if !self.renaming {
-                    self.reenter_selector();
+    /// 返回 `true` 表示按键应当交给重命名的输入框
+    fn update_input(&mut self, key: KeyEvent) -> bool {
+        // 输入新名称时按键都是文字，只有确定和取消
+        if self.renaming {
+            match key.code {
+                KeyCode::Enter => {
+                    self.renaming = false;
+                    self.player.name = self.rename_textarea.lines()[0].clone();
+                    self.update_required = true;
+                }
+                KeyCode::Esc => self.renaming = false,
+                KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right => (),
+                _ => return true,
+            }
+            return false;
+        }
+
+        let disable_flag = self.record_remove_entered;
+        match keymap::action(Screen::Manage, &key) {
+            Some(Action::Quit) => {
+                if self.record_remove_entered {
+                    self.record_remove_entered = false;
                 } else {
-                    return true;
+                    self.should_exit = true;
                 }
             }
-            KeyCode::Down => {
+            Some(Action::Switch) => {
+                self.reenter_selector();
+            }
+            Some(Action::Down) => {
                 if disable_flag {
                     return false;
                 }
@@ -448,7 +512,7 @@ impl ManageActivity<'_> {
                 }
                 self.record_scroll = self.record_scroll.position(i);
             }
-            KeyCode::Up => {
+            Some(Action::Up) => {
                 if disable_flag {
                     return false;
                 }
@@ -469,7 +533,7 @@ impl ManageActivity<'_> {
                 }
                 self.record_scroll = self.record_scroll.position(i);
             }
-            KeyCode::Left | KeyCode::Right => {
+            Some(Action::Left | Action::Right) => {
                 if disable_flag {
                     return false;
                 }
@@ -477,83 +541,89 @@ impl ManageActivity<'_> {
                     return false;
                 };
                 if self.record_state.selected_cell().is_none() {
-                    self.record_state.select_cell(Some((row, 3)));
+                    self.record_state.select_cell(Some((row, 8)));
                 } else {
                     self.record_state.select_cell(None);
                     self.record_state.select(Some(row));
                 }
             }
-            KeyCode::Char('r') => {
-                if !self.renaming {
-                    if disable_flag {
-                        return false;
-                    }
-                    let mut textarea = TextArea::default();
-                    textarea.set_block(Block::bordered().title(" 重命名 "));
-                    textarea.set_placeholder_text("请输入新的名称");
-                    self.rename_textarea = textarea;
-                    self.renaming = true;
-                } else {
-                    return true;
+            Some(Action::Rename) => {
+                if disable_flag {
+                    return false;
                 }
+                let mut textarea = TextArea::default();
+                textarea.set_block(Block::bordered().title(" 重命名 "));
+                textarea.set_placeholder_text("请输入新的名称");
+                self.rename_textarea = textarea;
+                self.renaming = true;
             }
-            KeyCode::Char('d') => {
-                if !self.renaming {
-                    if disable_flag {
-                        return false;
-                    }
+            Some(Action::Delete) => {
+                if disable_flag {
+                    return false;
+                }
 
-                    let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
-                    if self.player.id == self.self_id {
-                        dialog_manager.push(Dialog::new(
-                            " 否定 ",
-                            "你不能删除你自己",
-                            Alignment::Left,
-                            false,
-                            vec![String::from("确定")],
-                            None,
-                        ));
-                    } else {
-                        dialog_manager.push(Dialog::new(
-                            " 你确定吗？ ",
-                            "如果你删除该玩家，它将永远会消失。（很长时间！）",
-                            Alignment::Left,
-                            false,
-                            vec![String::from("确定"), String::from("取消")],
-                            Some(self.remove_choice.clone()),
-                        ));
-                    }
+                let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                if self.player.id == self.self_id {
+                    dialog_manager.push(Dialog::new(
+                        " 否定 ",
+                        "你不能删除你自己",
+                        Alignment::Left,
+                        false,
+                        vec![String::from("确定")],
+                        None,
+                    ));
                 } else {
-                    return true;
+                    dialog_manager.push(Dialog::new(
+                        " 你确定吗？ ",
+                        "如果你删除该玩家，它将永远会消失。（很长时间！）",
+                        Alignment::Left,
+                        false,
+                        vec![String::from("确定"), String::from("取消")],
+                        Some(self.remove_choice.clone()),
+                    ));
                 }
             }
-            KeyCode::Enter => {
-                if self.renaming {
-                    self.renaming = false;
-                    self.player.name = self.rename_textarea.lines()[0].clone();
-                    self.update_required = true;
-                } else if self.record_remove_entered {
+            Some(Action::Confirm) => {
+                if self.record_remove_entered {
                     let Some(index) = self.record_state.selected() else {
                         return false;
                     };
-                    self.player.records.remove(index);
+                    let record = self.player.records.remove(index);
+                    self.removed_records.push(record);
                     self.validate_player();
                     self.setup_rows();
                     self.setup_chart();
                     self.record_remove_entered = false;
-                    self.update_required = true;
                 } else if self.record_state.selected_cell().is_some() && !self.record_remove_entered
                 {
                     self.record_remove_entered = true;
                 }
             }
-            KeyCode::Esc => {
-                self.record_remove_entered = false;
-                self.renaming = false;
+            Some(Action::Verify) => {
+                if disable_flag {
+                    return false;
+                }
+                self.verify_replay();
             }
-            _ => {
-                return true;
+            Some(Action::Info) => {
+                if disable_flag {
+                    return false;
+                }
+                self.show_record_stats();
+            }
+            Some(Action::Replay) => {
+                if disable_flag {
+                    return false;
+                }
+                if let Some(record) = self
+                    .record_state
+                    .selected()
+                    .and_then(|x| self.player.records.get(x))
+                {
+                    self.open_replay = Some(replay_path(self.player.id, record.timestamp));
+                }
             }
+            _ => (),
         }
         false
     }
@@ -627,6 +697,96 @@ impl ManageActivity<'_> {
         }
     }
 
+    /// 重新执行选中记录的录像，检查分数是否与记录一致
+    fn verify_replay(&self) {
+        let Some(record) = self
+            .record_state
+            .selected()
+            .and_then(|x| self.player.records.get(x))
+        else {
+            return;
+        };
+        let path = replay_path(self.player.id, record.timestamp);
+        let message = if !path.exists() {
+            String::from("这条记录没有保存录像")
+        } else {
+            match Replay::load(&path).and_then(ReplayPlayer::new) {
+                Ok(mut player) => {
+                    if player.verify() && to_record(player.score()) == record.score {
+                        format!(
+                            "校验通过：重新执行 {} 步后得到 {} 分",
+                            player.len(),
+                            player.score()
+                        )
+                    } else {
+                        format!(
+                            "校验失败：重新执行录像得到 {} 分，但记录为 {} 分",
+                            player.score(),
+                            record.score
+                        )
+                    }
+                }
+                Err(e) => format!("无法读取录像: {e}"),
+            }
+        };
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            " 校验录像 ",
+            &message,
+            Alignment::Left,
+            true,
+            vec![String::from("确定")],
+            None,
+        ));
+    }
+
+    /// 弹出选中记录的详细统计
+    fn show_record_stats(&self) {
+        let Some(record) = self
+            .record_state
+            .selected()
+            .and_then(|x| self.player.records.get(x))
+        else {
+            return;
+        };
+        let message = format!(
+            indoc::indoc! {"
+                模式: {}    棋盘: {}×{}
+                分数: {}    最大地块: {}{}
+                用时: {}秒    种子: {}
+
+                有效移动: {} 次
+                  ↑ {}  ↓ {}  ← {}  → {}
+                合并地块: {} 次
+                撤销: {} 次
+            "},
+            record.mode().name(),
+            record.board_size(),
+            record.board_size(),
+            record.score,
+            record.max_tile,
+            if record.won { " ✔" } else { "" },
+            record.time,
+            format_seed(record.seed as u64),
+            record.moves,
+            record.moves_up,
+            record.moves_down,
+            record.moves_left,
+            record.moves_right,
+            record.merges,
+            record.undo_count,
+        );
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            " 对局详情 ",
+            &message,
+            Alignment::Left,
+            false,
+            vec![String::from("确定")],
+            None,
+        ));
+    }
+
     fn validate_player(&mut self) {
         self.player.best_score = 0;
         self.player.best_time = 0;
@@ -730,12 +890,7 @@ impl Activity for ManageActivity<'_> {
         if key.kind != KeyEventKind::Press {
             return;
         }
-        if !self.renaming && key.code == KeyCode::Char('q')
-            || !self.renaming && !self.record_remove_entered && key.code == KeyCode::Esc
-        {
-            self.should_exit = true;
-        }
-        if self.update_input(key.code) && self.renaming {
+        if self.update_input(key) {
             self.rename_textarea.input(event);
         }
     }
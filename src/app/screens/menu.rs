@@ -15,11 +15,16 @@ use tui_textarea::TextArea;
 use crate::{
     app::{
         ascii,
-        gameplay::colors,
+        data::RETRY_INTERVAL,
+        gameplay::{
+            BOARD_SIZES, DEFAULT_BOARD_SIZE, DEFAULT_TARGET_TILE, GameMode, TARGET_TILES,
+            bot::STRATEGIES, colors, daily_seed, save::SavedGame,
+        },
+        keymap::{self, Action, Screen},
         math::{Interpolation, inverse_lerp},
         structs::Player,
         time::TIME,
-        utils::{fade_in, rect_move},
+        utils::{day_start, fade_in, format_date_short, rect_move, today},
     },
     data_manager,
 };
@@ -33,6 +38,21 @@ use super::{
 pub struct MenuActivity<'a> {
     pub should_exit: bool,
     pub player: Player,
+    pub board_size: usize,
+    pub mode: GameMode,
+    /// 合成该地块即获胜
+    pub target_tile: u64,
+    /// 机器人演示所用的策略，`bot::STRATEGIES` 的下标
+    pub strategy: usize,
+    /// 选择继续游戏后交给游戏界面的存档
+    pub resume: Option<SavedGame>,
+    /// 玩家上次中途退出的对局
+    saved: Option<SavedGame>,
+    save_checked: bool,
+    /// 今天的每日挑战是否已经完成，查询到之前为 `None`
+    daily_done: Option<bool>,
+    /// 查询每日挑战失败后，到这个时间再重试
+    daily_retry_time: Duration,
     state: MenuState<'a>,
     focus: usize,
     selected_time: Duration,
@@ -46,6 +66,7 @@ pub struct MenuActivity<'a> {
     bg_rect_b: Rect,
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum MenuState<'a> {
     Login {
         username: TextArea<'a>,
@@ -106,7 +127,9 @@ impl MenuActivity<'_> {
     pub fn new(fade_in: bool) -> Self {
         Self {
             should_exit: false,
-            focus: 2,
+            focus: 3,
+            board_size: DEFAULT_BOARD_SIZE,
+            target_tile: DEFAULT_TARGET_TILE,
             fade_in,
             ..Default::default()
         }
@@ -161,7 +184,15 @@ impl MenuActivity<'_> {
             .title_alignment(Alignment::Right)
             .fg(tailwind::INDIGO.c50);
         if matches!(self.state, MenuState::Menu) {
-            block = block.title(" ( ↑ ↓ ) 切换 | ( ⏎ ) 确定 ─");
+            let key = |action| keymap::label(Screen::Menu, action);
+            block = block.title(format!(
+                " ( {} {} ) 切换 | ( {} {} ) 调整选项 | ( {} ) 确定 ─",
+                key(Action::Up),
+                key(Action::Down),
+                key(Action::Left),
+                key(Action::Right),
+                key(Action::Confirm)
+            ));
         }
         if matches!(
             self.state,
@@ -200,11 +231,18 @@ impl MenuActivity<'_> {
         let mut progress = inverse_lerp(0.0..=0.8_f32, self.selected_time.as_secs_f32());
         progress = 1.0 - interpolation.apply(progress);
 
-        let lines = Layout::vertical([Constraint::Length(1)].repeat(9)).split(menu);
+        let lines = Layout::vertical([Constraint::Length(1)].repeat(14)).split(menu);
         let options = indoc::indoc! {"
 
 
+            继续游戏
             进入游戏
+            游戏模式
+            胜利目标
+            每日挑战
+            谜题挑战
+            沙盒编辑
+            机器人演示
             账号登出
             管理玩家
             查看世界排名
@@ -249,10 +287,42 @@ impl MenuActivity<'_> {
                 } else {
                     tailwind::INDIGO.c50
                 }
+            } else if i == 2 && self.saved.is_none() || i == 6 && self.daily_done != Some(false) {
+                tailwind::ZINC.c500
             } else {
                 tailwind::INDIGO.c50
             };
 
+            let text = if i == 2 {
+                match self.saved {
+                    Some(ref saved) => format!(
+                        "{text} ‹{}×{} {}分›",
+                        saved.game.board_size(),
+                        saved.game.board_size(),
+                        saved.game.score()
+                    ),
+                    None => text.to_string(),
+                }
+            } else if i == 3 {
+                format!("{text} ‹{}×{}›", self.board_size, self.board_size)
+            } else if i == 4 {
+                format!("{text} ‹{}›", self.mode.name())
+            } else if i == 5 {
+                if self.mode.has_target() {
+                    format!("{text} ‹{}›", self.target_tile)
+                } else {
+                    format!("{text} ‹不判定›")
+                }
+            } else if i == 6 {
+                match self.daily_done {
+                    Some(true) => format!("{text} ‹今日已完成›"),
+                    _ => format!("{text} ‹{}›", format_date_short(day_start(today()))),
+                }
+            } else if i == 9 {
+                format!("{text} ‹{}›", STRATEGIES[self.strategy].0)
+            } else {
+                text.to_string()
+            };
             let text = if flag {
                 let spaces = [' ']
                     .repeat((10.0 * progress) as usize)
@@ -327,7 +397,7 @@ impl MenuActivity<'_> {
         {
             let resized =
                 area.width != self.bg_rect_a.width || area.height != self.bg_rect_a.height;
-            if self.app_time.as_secs() % 5 == 0 || resized {
+            if self.app_time.as_secs().is_multiple_of(5) || resized {
                 if !self.bg_changed || resized {
                     let length = 75;
                     let area_width = area.width as i32;
@@ -356,10 +426,73 @@ impl MenuActivity<'_> {
     }
 
     pub fn exiting_activity(&mut self) {
+        // 刚结束的一局可能保存或删除了存档，也可能完成了每日挑战
+        self.save_checked = false;
+        self.daily_done = None;
         self.state = MenuState::Exiting;
         self.transition_time = Duration::default();
     }
 
+    /// 没有存档时跳过“继续游戏”
+    fn first_option(&self) -> usize {
+        if self.saved.is_some() { 2 } else { 3 }
+    }
+
+    /// 向数据层查询玩家上次中途退出的对局
+    fn update_saved(&mut self) {
+        let Some(text) = data_manager!(load_game, self.player.clone()) else {
+            return;
+        };
+        self.save_checked = true;
+        self.saved = match text.ok().flatten().map(|x| SavedGame::decode(&x)) {
+            Some(Ok(saved)) => Some(saved),
+            Some(Err(e)) => {
+                let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                dialog_manager.push(Dialog::new(
+                    " 遇到问题 ",
+                    &format!("存档已损坏，无法继续上次的对局: {e}"),
+                    Alignment::Left,
+                    true,
+                    vec![String::from("确定")],
+                    None,
+                ));
+                None
+            }
+            None => None,
+        };
+        if self.saved.is_none() && self.focus == 2 {
+            self.focus = 3;
+        }
+    }
+
+    /// 查询玩家今天是否已经提交过每日挑战的成绩
+    fn update_daily(&mut self) {
+        if self.app_time < self.daily_retry_time {
+            return;
+        }
+        // 查询失败时保持未知，不能把默认玩家当成没有提交过
+        let player = match data_manager!(get_current_player) {
+            Some(Ok(player)) => player,
+            Some(Err(e)) => {
+                // 玩家不存在时不再重试，每日挑战保持不可选
+                self.daily_retry_time = if e.is_transient() {
+                    self.app_time + RETRY_INTERVAL
+                } else {
+                    Duration::MAX
+                };
+                return;
+            }
+            None => return,
+        };
+        let seed = daily_seed(today());
+        self.daily_done = Some(
+            player
+                .records
+                .iter()
+                .any(|x| x.mode() == GameMode::Daily && x.seed as u64 == seed),
+        );
+    }
+
     pub fn can_enter_another_activity(&self) -> bool {
         self.transition_time.as_secs_f32() >= 1.6
     }
@@ -369,10 +502,14 @@ impl MenuActivity<'_> {
             return None;
         }
         match self.focus {
-            2 => Some(AppState::Gameplay),
-            3 => Some(AppState::SwitchPlayer),
-            4 => Some(AppState::ManagePlayer),
-            5 => Some(AppState::Ranking),
+            2 | 3 => Some(AppState::Gameplay),
+            6 => Some(AppState::Daily),
+            7 => Some(AppState::Puzzles),
+            8 => Some(AppState::Sandbox),
+            9 => Some(AppState::Autoplay),
+            10 => Some(AppState::SwitchPlayer),
+            11 => Some(AppState::ManagePlayer),
+            12 => Some(AppState::Ranking),
             _ => None,
         }
     }
@@ -415,7 +552,7 @@ impl MenuActivity<'_> {
         if logged_in {
             let divs = Layout::vertical([
                 Constraint::Fill(1),
-                Constraint::Length(9),
+                Constraint::Length(15),
                 Constraint::Max(1),
             ])
             .split(area);
@@ -522,7 +659,7 @@ impl Activity for MenuActivity<'_> {
             let area = frame.area();
             let divs = Layout::vertical([
                 Constraint::Fill(1),
-                Constraint::Length(9),
+                Constraint::Length(15),
                 Constraint::Max(1),
             ])
             .split(area);
@@ -568,19 +705,9 @@ impl Activity for MenuActivity<'_> {
                         password.lines()[0].clone()
                     ) {
                         *login_pressed = false;
-                        if let Some(x) = x {
+                        if let Ok(x) = x {
                             self.player = x;
                             *logged_in = true;
-                        } else {
-                            let mut dialog_manger = DIALOG_MANAGER.write().unwrap();
-                            dialog_manger.push(Dialog::new(
-                                " 无法注册 ",
-                                "在注册时遇到问题",
-                                Alignment::Left,
-                                false,
-                                vec![String::from("确定")],
-                                None,
-                            ));
                         }
                     }
                 } else if let Some(x) = data_manager!(
@@ -588,20 +715,11 @@ impl Activity for MenuActivity<'_> {
                     username.lines()[0].clone(),
                     password.lines()[0].clone()
                 ) {
+                    // 失败的原因由 `data_manager!` 弹窗提示
                     *login_pressed = false;
-                    if let Some(x) = x {
+                    if let Ok(x) = x {
                         self.player = x;
                         *logged_in = true;
-                    } else {
-                        let mut dialog_manger = DIALOG_MANAGER.write().unwrap();
-                        dialog_manger.push(Dialog::new(
-                            " 登录失败 ",
-                            "账号或密码错误",
-                            Alignment::Left,
-                            false,
-                            vec![String::from("确定")],
-                            None,
-                        ));
                     }
                 }
 
@@ -620,6 +738,12 @@ impl Activity for MenuActivity<'_> {
             self.state = MenuState::Menu;
         }
 
+        if matches!(self.state, MenuState::Menu) && !self.save_checked {
+            self.update_saved();
+        } else if matches!(self.state, MenuState::Menu) && self.daily_done.is_none() {
+            self.update_daily();
+        }
+
         let Some(event) = event else {
             return;
         };
@@ -630,30 +754,90 @@ impl Activity for MenuActivity<'_> {
             return;
         }
         if matches!(self.state, MenuState::Menu) {
-            match key.code {
-                KeyCode::Up => {
+            let action = keymap::action(Screen::Menu, &key);
+            match action {
+                Some(Action::Up) => {
                     self.focus -= 1;
                     self.selected_time = Duration::default();
-                    if self.focus < 2 {
-                        self.focus = 6;
+                    if self.focus < self.first_option() {
+                        self.focus = 13;
                     }
                 }
-                KeyCode::Down => {
+                Some(Action::Down) => {
                     self.focus += 1;
                     self.selected_time = Duration::default();
-                    if self.focus > 6 {
-                        self.focus = 2;
+                    if self.focus > 13 {
+                        self.focus = self.first_option();
                     }
                 }
-                KeyCode::Enter => {
+                Some(Action::Left | Action::Right) if self.focus == 3 => {
+                    let index = BOARD_SIZES
+                        .iter()
+                        .position(|x| *x == self.board_size)
+                        .unwrap_or_default();
+                    let index = if action == Some(Action::Right) {
+                        (index + 1) % BOARD_SIZES.len()
+                    } else {
+                        (index + BOARD_SIZES.len() - 1) % BOARD_SIZES.len()
+                    };
+                    self.board_size = BOARD_SIZES[index];
+                    self.selected_time = Duration::default();
+                }
+                // 游戏模式和胜利目标只是选项，确定键也用来切换
+                Some(Action::Left | Action::Right | Action::Confirm) if self.focus == 4 => {
+                    self.mode = self
+                        .mode
+                        .cycle(&GameMode::FREE_PLAY, action != Some(Action::Left));
+                    self.selected_time = Duration::default();
+                }
+                Some(Action::Left | Action::Right | Action::Confirm) if self.focus == 5 => {
+                    let index = TARGET_TILES
+                        .iter()
+                        .position(|x| *x == self.target_tile)
+                        .unwrap_or_default();
+                    let index = if action == Some(Action::Left) {
+                        (index + TARGET_TILES.len() - 1) % TARGET_TILES.len()
+                    } else {
+                        (index + 1) % TARGET_TILES.len()
+                    };
+                    self.target_tile = TARGET_TILES[index];
+                    self.selected_time = Duration::default();
+                }
+                // 已经挑战过或者还没查到结果时不能进入
+                Some(Action::Confirm) if self.focus == 6 && self.daily_done == Some(true) => {
+                    let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                    dialog_manager.push(Dialog::new(
+                        " 每日挑战 ",
+                        &format!(
+                            "今天的挑战已经完成了，可以在世界排名中按 {} 查看每日排行，明天再来吧！",
+                            keymap::label(Screen::Ranking, Action::Mode)
+                        ),
+                        Alignment::Left,
+                        true,
+                        vec![String::from("确定")],
+                        None,
+                    ));
+                }
+                Some(Action::Confirm) if self.focus == 6 && self.daily_done.is_none() => {}
+                Some(Action::Left | Action::Right) if self.focus == 9 => {
+                    self.strategy = if action == Some(Action::Right) {
+                        (self.strategy + 1) % STRATEGIES.len()
+                    } else {
+                        (self.strategy + STRATEGIES.len() - 1) % STRATEGIES.len()
+                    };
+                    self.selected_time = Duration::default();
+                }
+                Some(Action::Confirm) => {
                     self.transition_time = Duration::default();
                     self.state = MenuState::Entering;
 
-                    if self.focus == 6 {
+                    if self.focus == 2 {
+                        self.resume = self.saved.take();
+                    } else if self.focus == 13 {
                         self.should_exit = true;
                     }
                 }
-                KeyCode::Char('q') | KeyCode::Esc => {
+                Some(Action::Quit) => {
                     self.should_exit = true;
                 }
                 _ => (),
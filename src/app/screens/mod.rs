@@ -1,16 +1,30 @@
-use std::{io::Result, time::Duration};
+use std::{io::Result, path::PathBuf, time::Duration};
 
 use crossterm::event::{self, Event};
-use dialog::DIALOG_MANAGER;
-use ratatui::{Frame, Terminal, prelude::Backend};
-
-use crate::data_manager;
-
+use dialog::{DIALOG_MANAGER, Dialog};
+use ratatui::{Frame, Terminal, layout::Alignment, prelude::Backend};
+
+use crate::{
+    app::data::RETRY_INTERVAL,
+    app::gameplay::{
+        DEFAULT_BOARD_SIZE, DEFAULT_TARGET_TILE, GameConfig, GameMode, daily_seed, puzzle::Puzzle,
+        random_seed, replay::Replay,
+    },
+    app::keymap::{KEYMAP, keymap_path},
+    app::time::TIME,
+    app::utils::today,
+    data_manager,
+};
+
+mod board;
 pub(crate) mod dialog;
 mod gameplay;
 mod manage;
 mod menu;
 mod oobe;
+mod puzzle;
+mod replay;
+mod sandbox;
 mod simple_ranking;
 
 pub trait Activity {
@@ -19,15 +33,28 @@ pub trait Activity {
     fn update(&mut self, event: Option<Event>);
 }
 
-#[derive(Default)]
+#[derive(Default, Clone, Copy)]
 pub enum AppState {
     FirstLaunch,
     #[default]
     MainMenu,
     Gameplay,
+    /// 机器人自动游玩
+    Autoplay,
+    /// 今天的每日挑战
+    Daily,
+    /// 谜题选择
+    Puzzles,
+    /// 正在解谜题
+    Puzzle,
+    /// 沙盒编辑器
+    Sandbox,
+    /// 从沙盒摆出的局面开始游戏
+    SandboxPlay,
     SwitchPlayer,
     ManagePlayer,
     Ranking,
+    Replay,
     Exit,
 }
 
@@ -42,11 +69,38 @@ pub struct App<'a> {
     menu_activity: Option<menu::MenuActivity<'a>>,
     oobe_activity: Option<oobe::OobeActivity<'a>>,
     remove_activity: Option<manage::ManageActivity<'a>>,
+    replay_activity: Option<replay::ReplayActivity>,
+    puzzle_activity: Option<puzzle::PuzzleActivity>,
+    /// 开始游戏后保留编辑器，退出游戏时摆好的局面还在
+    sandbox_activity: Option<sandbox::SandboxActivity>,
+    /// 选中的谜题，交给游戏界面
+    puzzle: Option<Puzzle>,
+    /// 退出录像回放后回到的界面
+    replay_return: AppState,
     gameplay_move_save: bool,
+    /// 查询当前玩家失败后，到这个时间（从启动时算起）再重试
+    player_retry_time: Duration,
 }
 
 impl App<'_> {
     pub fn new(first_launch: bool) -> Self {
+        let keymap = KEYMAP.read().unwrap();
+        if !keymap.warnings.is_empty() {
+            let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+            dialog_manager.push(Dialog::new(
+                " 按键配置有误 ",
+                &format!(
+                    "{} 中有无法理解的内容，这些行已被忽略:\n{}",
+                    keymap_path().display(),
+                    keymap.warnings.join("\n")
+                ),
+                Alignment::Left,
+                false,
+                vec![String::from("确定")],
+                None,
+            ));
+        }
+
         Self {
             first_launch,
             state_changed: true,
@@ -63,6 +117,57 @@ impl App<'_> {
         self.state = state;
         self.state_changed = true;
     }
+
+    /// 菜单中选中的棋盘大小
+    fn board_size(&self) -> usize {
+        self.menu_activity
+            .as_ref()
+            .map_or(DEFAULT_BOARD_SIZE, |x| x.board_size)
+    }
+
+    /// 菜单中选中的胜利目标
+    fn target_tile(&self) -> u64 {
+        self.menu_activity
+            .as_ref()
+            .map_or(DEFAULT_TARGET_TILE, |x| x.target_tile)
+    }
+
+    /// 菜单中选中的游戏模式
+    fn mode(&self) -> GameMode {
+        self.menu_activity
+            .as_ref()
+            .map_or(GameMode::default(), |x| x.mode)
+    }
+
+    /// 打开录像回放，退出后回到当前界面
+    fn open_replay(&mut self, path: PathBuf) {
+        let result = if path.exists() {
+            Replay::load(&path).and_then(replay::ReplayActivity::new)
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "这条记录没有保存录像",
+            ))
+        };
+        match result {
+            Ok(activity) => {
+                self.replay_activity = Some(activity);
+                self.replay_return = self.state;
+                self.change_state(AppState::Replay);
+            }
+            Err(e) => {
+                let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+                dialog_manager.push(Dialog::new(
+                    " 无法播放录像 ",
+                    &e.to_string(),
+                    Alignment::Left,
+                    true,
+                    vec![String::from("确定")],
+                    None,
+                ));
+            }
+        }
+    }
 }
 
 impl App<'_> {
@@ -88,7 +193,13 @@ impl App<'_> {
             let event = if !has_dialog { event } else { None };
 
             match self.state {
-                AppState::Gameplay => self.update_gameplay(frame, event),
+                AppState::Gameplay
+                | AppState::Autoplay
+                | AppState::Daily
+                | AppState::Puzzle
+                | AppState::SandboxPlay => self.update_gameplay(frame, event),
+                AppState::Puzzles => self.update_puzzles(frame, event),
+                AppState::Sandbox => self.update_sandbox(frame, event),
                 AppState::MainMenu => self.update_menu(frame, event),
                 AppState::SwitchPlayer => {
                     if !last_state_changed {
@@ -99,6 +210,7 @@ impl App<'_> {
                 AppState::Ranking => self.update_ranking(frame, event),
                 AppState::FirstLaunch => self.update_oobe(frame, event),
                 AppState::ManagePlayer => self.update_remove(frame, event),
+                AppState::Replay => self.update_replay(frame, event),
                 _ => todo!(),
             };
 
@@ -117,7 +229,8 @@ impl App<'_> {
     }
 
     fn update_remove(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
-        if self.state_changed {
+        // 从录像回放返回时保留原来的界面
+        if self.state_changed && self.remove_activity.is_none() {
             let self_id = self.menu_activity.as_ref().map_or(0, |x| x.player.id);
             self.remove_activity = Some(manage::ManageActivity::new(self_id));
         }
@@ -126,13 +239,64 @@ impl App<'_> {
         remove.draw(frame);
         remove.update(event);
 
-        if remove.should_exit {
+        if let Some(path) = remove.open_replay.take() {
+            self.open_replay(path);
+        } else if remove.should_exit {
             self.change_state(AppState::MainMenu);
             let x = std::mem::take(&mut self.remove_activity);
             drop(x);
         }
     }
 
+    fn update_replay(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
+        let replay = self.replay_activity.as_mut().unwrap();
+        replay.draw(frame);
+        replay.update(event);
+
+        if replay.should_exit {
+            self.change_state(self.replay_return);
+            let x = std::mem::take(&mut self.replay_activity);
+            drop(x);
+        }
+    }
+
+    fn update_puzzles(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
+        // 每次回到这里都重新读取，刚完成的谜题会打上勾
+        if self.state_changed && self.puzzle_activity.is_none() {
+            self.puzzle_activity = Some(puzzle::PuzzleActivity::new());
+        }
+
+        let puzzles = self.puzzle_activity.as_mut().unwrap();
+        puzzles.draw(frame);
+        puzzles.update(event);
+
+        if let Some(puzzle) = puzzles.selected.take() {
+            self.puzzle = Some(puzzle);
+            self.puzzle_activity = None;
+            self.change_state(AppState::Puzzle);
+        } else if puzzles.should_exit {
+            self.puzzle_activity = None;
+            self.change_state(AppState::MainMenu);
+        }
+    }
+
+    fn update_sandbox(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
+        if self.state_changed && self.sandbox_activity.is_none() {
+            self.sandbox_activity = Some(sandbox::SandboxActivity::new());
+        }
+
+        let sandbox = self.sandbox_activity.as_mut().unwrap();
+        sandbox.draw(frame);
+        sandbox.update(event);
+
+        if sandbox.play.is_some() {
+            self.change_state(AppState::SandboxPlay);
+        } else if sandbox.should_exit {
+            self.sandbox_activity = None;
+            self.change_state(AppState::MainMenu);
+        }
+    }
+
     fn update_oobe(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
         if self.state_changed {
             self.oobe_activity = Some(oobe::OobeActivity::new());
@@ -159,23 +323,39 @@ impl App<'_> {
     }
 
     fn update_ranking(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
-        if self.state_changed {
-            self.ranking_activity = Some(simple_ranking::RankingActivity::new());
+        if self.state_changed && self.ranking_activity.is_none() {
+            let mut ranking = simple_ranking::RankingActivity::new();
+            ranking.set_board_size(self.board_size());
+            ranking.set_mode(self.mode());
+            self.ranking_activity = Some(ranking);
         }
 
         let ranking = self.ranking_activity.as_mut().unwrap();
-        if !self.gameplay_move_save {
-            if let Some(player) = data_manager!(get_current_player) {
-                self.gameplay_move_save = true;
-                ranking.set_save(player);
-                ranking.by_score();
+        let now = TIME.read().unwrap().startup.elapsed();
+        if !self.gameplay_move_save && now >= self.player_retry_time {
+            // 查询失败时不用默认玩家代替，稍后再重试
+            match data_manager!(get_current_player) {
+                Some(Ok(player)) => {
+                    self.gameplay_move_save = true;
+                    ranking.set_save(player);
+                    ranking.by_score();
+                }
+                Some(Err(e)) if e.is_transient() => self.player_retry_time = now + RETRY_INTERVAL,
+                Some(Err(_)) => {
+                    // 玩家不存在，排行榜里就不标出自己的成绩
+                    self.gameplay_move_save = true;
+                    ranking.by_score();
+                }
+                None => {}
             }
         }
 
         ranking.draw(frame);
         ranking.update(event);
 
-        if ranking.should_exit {
+        if let Some(path) = ranking.open_replay.take() {
+            self.open_replay(path);
+        } else if ranking.should_exit {
             self.gameplay_move_save = false;
             self.ranking_activity = None;
             let x = std::mem::take(&mut self.ranking_activity);
@@ -199,11 +379,11 @@ impl App<'_> {
         menu.draw(frame);
         menu.update(event);
 
-        if menu.can_enter_another_activity() {
-            if let Some(next_state) = menu.next_state() {
-                self.change_state(next_state);
-                return;
-            }
+        if menu.can_enter_another_activity()
+            && let Some(next_state) = menu.next_state()
+        {
+            self.change_state(next_state);
+            return;
         }
 
         if menu.should_exit {
@@ -212,8 +392,37 @@ impl App<'_> {
     }
 
     fn update_gameplay(&mut self, frame: &mut Frame<'_>, event: Option<Event>) {
-        if self.state_changed {
-            self.gameplay_activity = Some(gameplay::GameplayActivity::new());
+        if self.state_changed && self.gameplay_activity.is_none() {
+            let autoplay = matches!(self.state, AppState::Autoplay);
+            // 机器人总是以经典模式游玩
+            let mode = if autoplay {
+                GameMode::Classic
+            } else {
+                self.mode()
+            };
+            let config = GameConfig {
+                mode,
+                target_tile: if mode.has_target() {
+                    self.target_tile()
+                } else {
+                    0
+                },
+                ..GameConfig::with_board_size(self.board_size())
+            };
+            self.gameplay_activity = Some(if autoplay {
+                let strategy = self.menu_activity.as_ref().map_or(0, |x| x.strategy);
+                gameplay::GameplayActivity::autoplay(config, random_seed(), strategy)
+            } else if matches!(self.state, AppState::Daily) {
+                gameplay::GameplayActivity::new(GameConfig::daily(), daily_seed(today()))
+            } else if let Some(puzzle) = self.puzzle.take() {
+                gameplay::GameplayActivity::puzzle(puzzle)
+            } else if let Some(cells) = self.sandbox_activity.as_mut().and_then(|x| x.play.take()) {
+                gameplay::GameplayActivity::sandbox(cells, random_seed())
+            } else if let Some(saved) = self.menu_activity.as_mut().and_then(|x| x.resume.take()) {
+                gameplay::GameplayActivity::resume(saved)
+            } else {
+                gameplay::GameplayActivity::new(config, random_seed())
+            });
             self.ranking_activity = Some(simple_ranking::RankingActivity::new());
         }
 
@@ -222,8 +431,13 @@ impl App<'_> {
         if !gameplay.show_ranking {
             gameplay.draw(frame);
             gameplay.update(event);
-            if gameplay.should_exit && gameplay.record_saved {
-                self.change_state(AppState::MainMenu);
+            if gameplay.should_exit && gameplay.record_saved && !gameplay.is_saving() {
+                // 解完谜题回到谜题列表，沙盒的对局回到编辑器
+                match self.state {
+                    AppState::Puzzle => self.change_state(AppState::Puzzles),
+                    AppState::SandboxPlay => self.change_state(AppState::Sandbox),
+                    _ => self.change_state(AppState::MainMenu),
+                }
                 let x = std::mem::take(&mut self.gameplay_activity);
                 drop(x);
                 let x = std::mem::take(&mut self.ranking_activity);
@@ -233,6 +447,8 @@ impl App<'_> {
             let ranking = self.ranking_activity.as_mut().unwrap();
             if !self.gameplay_move_save {
                 self.gameplay_move_save = true;
+                ranking.set_board_size(gameplay.board_size());
+                ranking.set_mode(gameplay.mode());
                 ranking.set_save(gameplay.get_save());
                 ranking.by_score();
             }
@@ -240,7 +456,9 @@ impl App<'_> {
             ranking.draw(frame);
             ranking.update(event);
 
-            if ranking.should_exit {
+            if let Some(path) = ranking.open_replay.take() {
+                self.open_replay(path);
+            } else if ranking.should_exit {
                 ranking.reset();
                 self.gameplay_move_save = false;
                 gameplay.show_ranking = false;
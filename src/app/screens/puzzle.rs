@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyEventKind};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Layout, Rect},
+    style::{Color, Style, Stylize, palette::tailwind},
+    text::Text,
+    widgets::{Block, BorderType, Cell, HighlightSpacing, Paragraph, Row, Table, TableState},
+};
+
+use crate::{
+    app::{
+        data::RETRY_INTERVAL,
+        gameplay::puzzle::{self, Puzzle, puzzle_dir},
+        keymap::{self, Action, Screen},
+        structs::Player,
+        time::TIME,
+        utils::fade_in,
+    },
+    data_manager,
+};
+
+use super::Activity;
+
+/// 谜题选择界面
+#[derive(Default)]
+pub struct PuzzleActivity {
+    puzzles: Vec<Puzzle>,
+    /// 无法读取的谜题文件
+    errors: Vec<String>,
+    player: Option<Player>,
+    /// 查询当前玩家失败后，到这个时间再重试
+    player_retry_time: Duration,
+    state: TableState,
+    app_time: Duration,
+
+    pub should_exit: bool,
+    /// 选中的谜题，等待开始
+    pub selected: Option<Puzzle>,
+}
+
+impl PuzzleActivity {
+    pub fn new() -> Self {
+        let (puzzles, errors) = puzzle::load_all();
+        let mut this = Self {
+            puzzles,
+            errors: errors
+                .into_iter()
+                .map(|(path, e)| format!("{}: {e}", path.display()))
+                .collect(),
+            ..Default::default()
+        };
+        this.state.select(Some(0));
+        this
+    }
+
+    fn is_solved(&self, puzzle: &Puzzle) -> bool {
+        self.player.as_ref().is_some_and(|x| puzzle.solved_by(x))
+    }
+
+    fn move_row(&mut self, forward: bool) {
+        let len = self.puzzles.len();
+        if len == 0 {
+            return;
+        }
+        let i = self.state.selected().unwrap_or_default();
+        self.state.select(Some(if forward {
+            (i + 1) % len
+        } else {
+            (i + len - 1) % len
+        }));
+    }
+
+    fn update_input(&mut self, event: Event) {
+        let event::Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match keymap::action(Screen::Puzzles, &key) {
+            Some(Action::Quit) => self.should_exit = true,
+            Some(Action::Up) => self.move_row(false),
+            Some(Action::Down) => self.move_row(true),
+            Some(Action::Confirm) => {
+                self.selected = self
+                    .state
+                    .selected()
+                    .and_then(|x| self.puzzles.get(x))
+                    .cloned();
+            }
+            _ => (),
+        }
+    }
+
+    fn render_table(&mut self, frame: &mut Frame<'_>, area: Rect) {
+        let header = ["", "名称", "棋盘", "目标", "步数", "来源"]
+            .into_iter()
+            .map(Cell::from)
+            .collect::<Row>()
+            .style(
+                Style::default()
+                    .fg(tailwind::INDIGO.c50)
+                    .bg(tailwind::INDIGO.c700),
+            )
+            .height(1);
+        let rows = self.puzzles.iter().map(|puzzle| {
+            let solved = self.is_solved(puzzle);
+            let size = puzzle.board_size();
+            [
+                Cell::from(if solved { "✔" } else { "" }),
+                Cell::from(puzzle.name.as_str()),
+                Cell::from(format!("{size}×{size}")),
+                Cell::from(puzzle.goal_text()),
+                Cell::from(
+                    puzzle
+                        .move_limit
+                        .map_or(String::from("不限"), |x| x.to_string()),
+                ),
+                Cell::from(if puzzle.bundled {
+                    "内置"
+                } else {
+                    "自定义"
+                }),
+            ]
+            .into_iter()
+            .collect::<Row>()
+            .style(Style::new().fg(if solved {
+                tailwind::GREEN.c300
+            } else {
+                tailwind::INDIGO.c50
+            }))
+        });
+        let t = Table::new(
+            rows,
+            [
+                Constraint::Length(2),
+                Constraint::Fill(1),
+                Constraint::Length(6),
+                Constraint::Length(12),
+                Constraint::Length(6),
+                Constraint::Length(8),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(Style::new().bg(tailwind::INDIGO.c400))
+        .highlight_symbol(Text::from(" > "))
+        .bg(Color::Reset)
+        .highlight_spacing(HighlightSpacing::Always)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .fg(tailwind::INDIGO.c300)
+                .title(" 谜题 "),
+        );
+        frame.render_stateful_widget(t, area, &mut self.state);
+    }
+
+    fn render_footer(&self, frame: &mut Frame<'_>, area: Rect) {
+        let solved = self.puzzles.iter().filter(|x| self.is_solved(x)).count();
+        let key = |action| keymap::label(Screen::Puzzles, action);
+        let mut text = format!(
+            "({}) 退出 | ({} {}) 选择 | ({}) 开始 | 已完成 {solved}/{}\n自定义谜题放在 {}",
+            key(Action::Quit),
+            key(Action::Up),
+            key(Action::Down),
+            key(Action::Confirm),
+            self.puzzles.len(),
+            puzzle_dir().display()
+        );
+        if !self.errors.is_empty() {
+            text += &format!(
+                " | {} 个文件无法读取: {}",
+                self.errors.len(),
+                self.errors[0]
+            );
+        }
+        let footer = Paragraph::new(text)
+            .fg(tailwind::INDIGO.c100)
+            .block(
+                Block::bordered()
+                    .border_type(BorderType::Rounded)
+                    .fg(tailwind::INDIGO.c300),
+            )
+            .alignment(Alignment::Center);
+        frame.render_widget(footer, area);
+    }
+}
+
+impl Activity for PuzzleActivity {
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        let [table, footer] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(4)]).areas(frame.area());
+        self.render_table(frame, table);
+        self.render_footer(frame, footer);
+        fade_in(frame, 0.5, self.app_time.as_secs_f32(), None);
+    }
+
+    fn update(&mut self, event: Option<Event>) {
+        {
+            let time = TIME.read().unwrap();
+            self.app_time += time.delta;
+        }
+
+        if self.player.is_none() && self.app_time >= self.player_retry_time {
+            // 查询失败时不用默认玩家代替，稍后再重试
+            match data_manager!(get_current_player) {
+                Some(Ok(player)) => self.player = Some(player),
+                Some(Err(e)) if e.is_transient() => {
+                    self.player_retry_time = self.app_time + RETRY_INTERVAL
+                }
+                // 玩家不存在时不再重试，谜题都显示为未解出
+                Some(Err(_)) => self.player_retry_time = Duration::MAX,
+                None => {}
+            }
+        }
+
+        if let Some(event) = event {
+            self.update_input(event);
+        }
+    }
+}
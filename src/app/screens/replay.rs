@@ -0,0 +1,324 @@
+use std::{io, time::Duration};
+
+use crossterm::event::{Event, KeyEventKind, MouseButton, MouseEventKind};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Style, Stylize, palette::tailwind},
+    widgets::{Block, BorderType, Borders, Gauge, Padding, Paragraph},
+};
+
+use crate::app::{
+    gameplay::{
+        format_seed, fx,
+        replay::{Replay, ReplayPlayer},
+    },
+    keymap::{self, Action, Screen},
+    math::lerpf,
+    time::TIME,
+    utils::{fade_in, format_datetime},
+};
+
+use super::{Activity, board::BoardView};
+
+const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const DEFAULT_SPEED: usize = 2;
+
+/// 回放时两步之间最长的等待，跳过玩家长时间的思考
+const MAX_IDLE: Duration = Duration::from_millis(1500);
+
+pub struct ReplayActivity {
+    player: ReplayPlayer,
+    board: BoardView,
+    /// 录像中的时间
+    clock: Duration,
+    playing: bool,
+    speed: usize,
+    show_score: u64,
+    timeline: Rect,
+    app_time: Duration,
+
+    pub should_exit: bool,
+}
+
+impl ReplayActivity {
+    pub fn new(replay: Replay) -> io::Result<Self> {
+        let mut player = ReplayPlayer::new(replay)?;
+        let animations = player.reset();
+        Ok(Self {
+            board: BoardView::new(player.replay().board_size, animations),
+            player,
+            clock: Duration::ZERO,
+            playing: true,
+            speed: DEFAULT_SPEED,
+            show_score: 0,
+            timeline: Rect::default(),
+            app_time: Duration::ZERO,
+            should_exit: false,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.player.len()
+    }
+
+    fn is_end(&self) -> bool {
+        self.player.position() >= self.len()
+    }
+
+    /// 第 `position` 步发生的时间
+    fn step_time(&self, position: usize) -> Duration {
+        position
+            .checked_sub(1)
+            .and_then(|x| self.player.replay().steps.get(x))
+            .map_or(Duration::ZERO, |x| x.time)
+    }
+
+    fn seek(&mut self, position: usize) {
+        self.player.seek(position);
+        self.board.snap(self.player.cells());
+        self.clock = self.step_time(self.player.position());
+    }
+
+    fn step_forward(&mut self) {
+        if let Some(animations) = self.player.step() {
+            self.board.animate(animations);
+        }
+        self.clock = self.step_time(self.player.position());
+    }
+
+    fn advance(&mut self, delta: Duration) {
+        if !self.playing {
+            return;
+        }
+        self.clock += delta.mul_f32(SPEEDS[self.speed]);
+        while !self.is_end() {
+            let next = self.step_time(self.player.position() + 1);
+            if next.saturating_sub(self.clock) > MAX_IDLE {
+                self.clock = next - MAX_IDLE;
+            }
+            if next > self.clock {
+                break;
+            }
+            if let Some(animations) = self.player.step() {
+                self.board.animate(animations);
+            }
+        }
+        if self.is_end() {
+            self.playing = false;
+        }
+    }
+
+    fn update_input(&mut self, event: Event) {
+        match event {
+            Event::Key(key) => {
+                if key.kind != KeyEventKind::Press {
+                    return;
+                }
+                let jump = (self.len() / 10).max(1);
+                let position = self.player.position();
+                let Some(action) = keymap::action(Screen::Replay, &key) else {
+                    return;
+                };
+                match action {
+                    Action::Quit => self.should_exit = true,
+                    Action::Pause => {
+                        if self.is_end() {
+                            self.seek(0);
+                            self.playing = true;
+                        } else {
+                            self.playing = !self.playing;
+                        }
+                    }
+                    Action::Right => {
+                        self.playing = false;
+                        self.step_forward();
+                    }
+                    Action::Left => {
+                        self.playing = false;
+                        self.seek(position.saturating_sub(1));
+                    }
+                    Action::Up => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+                    Action::Down => self.speed = self.speed.saturating_sub(1),
+                    Action::Back => self.seek(position.saturating_sub(jump)),
+                    Action::Forward => self.seek(position + jump),
+                    Action::Top => self.seek(0),
+                    Action::End => self.seek(self.len()),
+                    _ => (),
+                }
+            }
+            Event::Mouse(mouse) => {
+                if !matches!(
+                    mouse.kind,
+                    MouseEventKind::Down(MouseButton::Left)
+                        | MouseEventKind::Drag(MouseButton::Left)
+                ) {
+                    return;
+                }
+                let timeline = self.timeline;
+                if timeline.width == 0 || !timeline.contains((mouse.column, mouse.row).into()) {
+                    return;
+                }
+                let progress = (mouse.column - timeline.x) as f32
+                    / timeline.width.saturating_sub(1).max(1) as f32;
+                self.seek((progress * self.len() as f32).round() as usize);
+            }
+            _ => (),
+        }
+    }
+
+    fn replay_draw(&mut self, frame: &mut Frame<'_>) {
+        let area = frame.area();
+
+        let [title, div, timeline] = Layout::vertical([
+            Constraint::Max(3),
+            Constraint::Min(0),
+            Constraint::Length(3),
+        ])
+        .areas(area);
+
+        let outer_subdiv = Layout::horizontal(vec![
+            Constraint::Fill(1),
+            Constraint::Max(div.height * 2 + 6),
+            Constraint::Max((div.height / 2) + 7),
+            Constraint::Fill(1),
+        ])
+        .flex(Flex::Center)
+        .split(div);
+
+        let replay = self.player.replay();
+        let header = Paragraph::new(format!(
+            "录像回放 | {}",
+            if replay.timestamp != 0 {
+                format_datetime(replay.timestamp)
+            } else {
+                String::from("未知时间")
+            }
+        ))
+        .style(Style::default().fg(tailwind::GREEN.c50))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .alignment(Alignment::Center);
+        frame.render_widget(header, title);
+
+        self.board.draw(frame, outer_subdiv[1], self.player.cells());
+
+        self.show_score =
+            lerpf(self.show_score as f32..=self.player.score() as f32, 0.1).round() as u64;
+
+        let replay = self.player.replay();
+        let total = replay.steps.last().map_or(Duration::ZERO, |x| x.time);
+        let state = if self.playing {
+            "▶ 播放中"
+        } else if self.is_end() {
+            "■ 已结束"
+        } else {
+            "⏸ 已暂停"
+        };
+        let key = |action| keymap::label(Screen::Replay, action);
+        let stats_detail = Paragraph::new(format!(
+            indoc::indoc! {"
+                分数: {:04}
+                记录: {:04}
+                棋盘: {}×{}
+
+                🎲 种子:
+                {}
+
+                ⌚ 进度:
+                {} / {} 步
+                {}秒 / {}秒
+
+                {} | 倍速 ×{}
+
+                🎮 如何控制:
+                {} 播放/暂停
+                {} {} 单步
+                {} {} 调整倍速
+                {} {} 快退/快进
+                {} {} 跳到首尾
+
+                键入'{}'以退出回放
+            "},
+            self.show_score,
+            replay.score,
+            replay.board_size,
+            replay.board_size,
+            format_seed(replay.seed),
+            self.player.position(),
+            self.len(),
+            self.clock.as_secs(),
+            total.as_secs(),
+            state,
+            SPEEDS[self.speed],
+            key(Action::Pause),
+            key(Action::Left),
+            key(Action::Right),
+            key(Action::Up),
+            key(Action::Down),
+            key(Action::Back),
+            key(Action::Forward),
+            key(Action::Top),
+            key(Action::End),
+            key(Action::Quit),
+        ))
+        .style(Style::default().fg(tailwind::INDIGO.c300))
+        .block(
+            Block::default()
+                .title("Replay")
+                .title_alignment(Alignment::Left)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .padding(Padding::new(1, 1, 1, 1)),
+        )
+        .alignment(Alignment::Left);
+        frame.render_widget(stats_detail, outer_subdiv[2]);
+
+        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[0]);
+        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[3]);
+
+        let ratio = if self.len() == 0 {
+            0.0
+        } else {
+            self.player.position() as f64 / self.len() as f64
+        };
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(" 时间轴 ")
+            .title_bottom("( 鼠标 ) 点击或拖动以跳转")
+            .title_alignment(Alignment::Right)
+            .fg(tailwind::INDIGO.c300);
+        self.timeline = block.inner(timeline);
+        let gauge = Gauge::default()
+            .block(block)
+            .gauge_style(tailwind::INDIGO.c400)
+            .use_unicode(true)
+            .label(format!("{}/{}", self.player.position(), self.len()))
+            .ratio(ratio);
+        frame.render_widget(gauge, timeline);
+
+        fade_in(frame, 0.8, self.app_time.as_secs_f32(), None);
+    }
+}
+
+impl Activity for ReplayActivity {
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        self.replay_draw(frame);
+    }
+
+    fn update(&mut self, event: Option<Event>) {
+        let delta = {
+            let time = TIME.read().unwrap();
+            time.delta
+        };
+        self.app_time += delta;
+        self.advance(delta);
+
+        if let Some(event) = event {
+            self.update_input(event);
+        }
+    }
+}
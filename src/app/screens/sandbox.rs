@@ -0,0 +1,469 @@
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind};
+use ratatui::{
+    Frame,
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Color, Style, Stylize, palette::tailwind},
+    widgets::{Block, BorderType, Borders, Clear, Padding, Paragraph},
+};
+use tui_textarea::TextArea;
+
+use crate::app::{
+    gameplay::{
+        BOARD_SIZES, DEFAULT_BOARD_SIZE, GameConfig, Grid, TARGET_TILES, fx, new_grid,
+        puzzle::{PUZZLE_EXTENSION, Puzzle, PuzzleGoal, puzzle_dir},
+        random_seed,
+    },
+    keymap::{self, Action, Screen},
+    structs::{Cell, Vec2},
+    time::TIME,
+    utils::{fade_in, get_time_millis},
+};
+
+use super::{
+    Activity,
+    board::BoardView,
+    dialog::{DIALOG_MANAGER, Dialog},
+};
+
+/// 编辑器里能摆出的最大指数
+const MAX_EXPONENT: u8 = 30;
+
+/// 导出谜题时可选的步数限制
+const MOVE_LIMITS: [Option<u32>; 5] = [None, Some(10), Some(20), Some(50), Some(100)];
+
+/// 沙盒：手动摆放地块，从这个局面开始游戏或导出为谜题
+pub struct SandboxActivity {
+    cells: Grid,
+    cursor: Vec2,
+    board: BoardView,
+    /// 上一帧棋盘所在的位置，鼠标点击时用来找出格子
+    board_area: Rect,
+    /// `TARGET_TILES` 的下标，等于长度时表示只剩一块
+    goal: usize,
+    /// `MOVE_LIMITS` 的下标
+    move_limit: usize,
+    name_input: Option<TextArea<'static>>,
+    app_time: Duration,
+
+    pub should_exit: bool,
+    /// 按下开始后交给游戏界面的局面
+    pub play: Option<Grid>,
+}
+
+impl SandboxActivity {
+    pub fn new() -> Self {
+        Self {
+            cells: new_grid(DEFAULT_BOARD_SIZE),
+            cursor: Vec2 { x: 0, y: 0 },
+            board: BoardView::new(DEFAULT_BOARD_SIZE, Vec::new()),
+            board_area: Rect::default(),
+            goal: TARGET_TILES.len(),
+            move_limit: 0,
+            name_input: None,
+            app_time: Duration::default(),
+            should_exit: false,
+            play: None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn goal(&self) -> PuzzleGoal {
+        match TARGET_TILES.get(self.goal) {
+            Some(tile) => PuzzleGoal::Tile(*tile),
+            None => PuzzleGoal::SingleTile,
+        }
+    }
+
+    /// 换一种棋盘大小，原来的地块尽量保留在左上角
+    fn switch_board_size(&mut self, forward: bool) {
+        let index = BOARD_SIZES
+            .iter()
+            .position(|x| *x == self.size())
+            .unwrap_or_default();
+        let size = if forward {
+            BOARD_SIZES[(index + 1) % BOARD_SIZES.len()]
+        } else {
+            BOARD_SIZES[(index + BOARD_SIZES.len() - 1) % BOARD_SIZES.len()]
+        };
+        let mut cells = new_grid(size);
+        for (row, old) in cells.iter_mut().zip(&self.cells) {
+            for (cell, old) in row.iter_mut().zip(old) {
+                *cell = *old;
+            }
+        }
+        self.cells = cells;
+        self.cursor = Vec2 {
+            x: self.cursor.x.min(size - 1),
+            y: self.cursor.y.min(size - 1),
+        };
+        self.board = BoardView::new(size, Vec::new());
+    }
+
+    fn set_cell(&mut self, at: Vec2, exponent: u8) {
+        self.cells[at.x][at.y] = Cell::new(exponent.min(MAX_EXPONENT));
+        self.board.snap(&self.cells);
+    }
+
+    /// 调整地块的指数，减到 0 时清空
+    fn step_cell(&mut self, at: Vec2, up: bool) {
+        let exponent = self.cells[at.x][at.y].exponent();
+        let exponent = if up {
+            exponent.saturating_add(1).max(1)
+        } else {
+            exponent.saturating_sub(1)
+        };
+        self.set_cell(at, exponent);
+    }
+
+    fn move_cursor(&mut self, direction: Action) {
+        let size = self.size();
+        let Vec2 { x, y } = self.cursor;
+        self.cursor = match direction {
+            Action::Up => Vec2 {
+                x: (x + size - 1) % size,
+                y,
+            },
+            Action::Down => Vec2 {
+                x: (x + 1) % size,
+                y,
+            },
+            Action::Left => Vec2 {
+                x,
+                y: (y + size - 1) % size,
+            },
+            Action::Right => Vec2 {
+                x,
+                y: (y + 1) % size,
+            },
+            _ => self.cursor,
+        };
+    }
+
+    fn is_empty(&self) -> bool {
+        self.cells.iter().flatten().all(|x| x.empty())
+    }
+
+    fn warn_empty(&self) {
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            " 棋盘是空的 ",
+            "请先摆上至少一个地块",
+            Alignment::Left,
+            false,
+            vec![String::from("确定")],
+            None,
+        ));
+    }
+
+    fn export(&self, name: &str) {
+        let name = name.trim();
+        let puzzle = Puzzle {
+            name: if name.is_empty() {
+                String::from("沙盒谜题")
+            } else {
+                name.to_string()
+            },
+            goal: self.goal(),
+            move_limit: MOVE_LIMITS[self.move_limit],
+            spawn: GameConfig::default().spawn,
+            undo_budget: GameConfig::default().undo_budget,
+            seed: random_seed(),
+            cells: self.cells.clone(),
+            bundled: false,
+        };
+        let path = puzzle_dir().join(format!("sandbox_{}.{PUZZLE_EXTENSION}", get_time_millis()));
+        let (title, message) = match puzzle.save(&path) {
+            Ok(()) => (" 导出成功 ", format!("谜题已保存到 {}", path.display())),
+            Err(e) => (" 遇到问题 ", format!("导出谜题失败: {e}")),
+        };
+        let mut dialog_manager = DIALOG_MANAGER.write().unwrap();
+        dialog_manager.push(Dialog::new(
+            title,
+            &message,
+            Alignment::Left,
+            true,
+            vec![String::from("确定")],
+            None,
+        ));
+    }
+
+    /// 鼠标所在的格子
+    fn cell_at(&self, column: u16, row: u16) -> Option<Vec2> {
+        let position = Rect::new(column, row, 1, 1);
+        BoardView::layout(self.board_area, self.size())
+            .iter()
+            .enumerate()
+            .find_map(|(x, rects)| {
+                rects
+                    .iter()
+                    .position(|rect| rect.intersects(position))
+                    .map(|y| Vec2 { x, y })
+            })
+    }
+
+    fn name_update_input(&mut self, event: Event) {
+        let Some(ref mut textarea) = self.name_input else {
+            return;
+        };
+        let event::Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        match key.code {
+            KeyCode::Esc => self.name_input = None,
+            KeyCode::Enter => {
+                let name = textarea.lines()[0].clone();
+                self.name_input = None;
+                self.export(&name);
+            }
+            _ => {
+                textarea.input(key);
+            }
+        }
+    }
+
+    fn update_input(&mut self, event: Event) {
+        if let Event::Mouse(mouse) = event {
+            let Some(at) = self.cell_at(mouse.column, mouse.row) else {
+                return;
+            };
+            match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    self.cursor = at;
+                    self.step_cell(at, true);
+                }
+                MouseEventKind::Down(MouseButton::Right) => {
+                    self.cursor = at;
+                    self.set_cell(at, 0);
+                }
+                MouseEventKind::ScrollUp => self.step_cell(at, true),
+                MouseEventKind::ScrollDown => self.step_cell(at, false),
+                _ => (),
+            }
+            return;
+        }
+
+        let event::Event::Key(key) = event else {
+            return;
+        };
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        let Some(action) = keymap::action(Screen::Sandbox, &key) else {
+            // 数字键直接摆放对应的地块，不经过按键配置
+            if let KeyCode::Char(c @ '1'..='9') = key.code {
+                self.set_cell(self.cursor, c as u8 - b'0');
+            }
+            return;
+        };
+        match action {
+            Action::Quit => self.should_exit = true,
+            Action::Up | Action::Down | Action::Left | Action::Right => self.move_cursor(action),
+            Action::Increase => self.step_cell(self.cursor, true),
+            Action::Decrease => self.step_cell(self.cursor, false),
+            Action::Erase => self.set_cell(self.cursor, 0),
+            Action::Clear => {
+                self.cells = new_grid(self.size());
+                self.board.snap(&self.cells);
+            }
+            Action::Smaller => self.switch_board_size(false),
+            Action::Larger => self.switch_board_size(true),
+            Action::Goal => self.goal = (self.goal + 1) % (TARGET_TILES.len() + 1),
+            Action::Moves => self.move_limit = (self.move_limit + 1) % MOVE_LIMITS.len(),
+            Action::Confirm => {
+                if self.is_empty() {
+                    self.warn_empty();
+                } else {
+                    self.play = Some(self.cells.clone());
+                }
+            }
+            Action::Export => {
+                if self.is_empty() {
+                    self.warn_empty();
+                } else {
+                    let mut textarea = TextArea::default();
+                    textarea.set_block(Block::bordered().title(" 谜题名称 "));
+                    textarea.set_placeholder_text("沙盒谜题");
+                    self.name_input = Some(textarea);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn draw_cursor(&self, frame: &mut Frame<'_>) {
+        let cols = BoardView::layout(self.board_area, self.size());
+        let rect = cols[self.cursor.x][self.cursor.y];
+        // 只改边框的颜色，地块的数字保持原样
+        let block = Block::bordered()
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(tailwind::AMBER.c300));
+        frame.render_widget(block, rect);
+    }
+
+    fn draw_name_input(&self, frame: &mut Frame<'_>) {
+        let Some(ref textarea) = self.name_input else {
+            return;
+        };
+
+        let buf = frame.buffer_mut();
+        buf.content.iter_mut().for_each(|x| {
+            if let Color::Rgb(r, g, b) = x.fg {
+                x.fg = Color::Rgb(
+                    r.saturating_sub(100),
+                    g.saturating_sub(100),
+                    b.saturating_sub(100),
+                );
+            }
+        });
+
+        let [_, chunk, _] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Percentage(40),
+            Constraint::Fill(1),
+        ])
+        .areas(frame.area());
+        let [_, dialog, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ])
+        .areas(chunk);
+
+        frame.render_widget(Clear, dialog);
+        let block = Block::bordered()
+            .border_type(BorderType::Double)
+            .title_bottom("( ⏎ ) 导出 | ( ESC ) 取消")
+            .title_alignment(Alignment::Right)
+            .fg(tailwind::WHITE);
+        frame.render_widget(&block, dialog);
+        frame.render_widget(textarea, block.inner(dialog));
+    }
+
+    fn sandbox_draw(&mut self, frame: &mut Frame<'_>) {
+        let area = frame.area();
+
+        let [title, div] = Layout::vertical([Constraint::Max(3), Constraint::Min(0)]).areas(area);
+
+        let outer_subdiv = Layout::horizontal(vec![
+            Constraint::Fill(1),
+            Constraint::Max(area.height * 2 + 6),
+            Constraint::Max((area.height / 2) + 7),
+            Constraint::Fill(1),
+        ])
+        .flex(Flex::Center)
+        .split(div);
+
+        let header = Paragraph::new("2048 小游戏 | 沙盒编辑")
+            .style(Style::default().fg(tailwind::GREEN.c50))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .alignment(Alignment::Center);
+        frame.render_widget(header, title);
+
+        self.board_area = outer_subdiv[1];
+        self.board.draw(frame, self.board_area, &self.cells);
+        self.draw_cursor(frame);
+
+        let size = self.size();
+        let selected = self.cells[self.cursor.x][self.cursor.y];
+        let key = |action| keymap::label(Screen::Sandbox, action);
+        let stats_detail = Paragraph::new(format!(
+            indoc::indoc! {"
+                棋盘: {}×{}
+                选中: {}
+
+                🧩 导出谜题:
+                目标: {}
+                步数: {}
+
+                🎮 如何控制:
+                {} {} {} {} 选择格子
+                1-9 摆放 2-512
+                {} {} 调整数值
+                {} 清除 | {} 清空
+                {} {} 棋盘大小
+                {} 目标 | {} 步数
+
+                🖱 鼠标:
+                左键 增大 | 右键 清除
+                滚轮 调整数值
+
+                {} 从这里开始游戏
+                {} 导出为谜题
+                键入'{}'以退出
+            "},
+            size,
+            size,
+            if selected.empty() {
+                String::from("空")
+            } else {
+                selected.value().to_string()
+            },
+            self.goal().text(),
+            MOVE_LIMITS[self.move_limit].map_or(String::from("不限"), |x| x.to_string()),
+            key(Action::Left),
+            key(Action::Up),
+            key(Action::Down),
+            key(Action::Right),
+            key(Action::Increase),
+            key(Action::Decrease),
+            key(Action::Erase),
+            key(Action::Clear),
+            key(Action::Smaller),
+            key(Action::Larger),
+            key(Action::Goal),
+            key(Action::Moves),
+            key(Action::Confirm),
+            key(Action::Export),
+            key(Action::Quit),
+        ))
+        .style(Style::default().fg(tailwind::INDIGO.c300))
+        .block(
+            Block::default()
+                .border_style(Style::default().fg(tailwind::INDIGO.c300))
+                .title("Sandbox")
+                .title_alignment(Alignment::Left)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .padding(Padding::new(1, 1, 1, 1)),
+        )
+        .alignment(Alignment::Left);
+        frame.render_widget(stats_detail, outer_subdiv[2]);
+
+        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[0]);
+        frame.render_widget(fx::gen_matrix(self.app_time), outer_subdiv[3]);
+
+        self.draw_name_input(frame);
+
+        fade_in(frame, 0.8, self.app_time.as_secs_f32(), None);
+    }
+}
+
+impl Activity for SandboxActivity {
+    fn draw(&mut self, frame: &mut Frame<'_>) {
+        self.sandbox_draw(frame);
+    }
+
+    fn update(&mut self, event: Option<Event>) {
+        self.app_time += TIME.read().unwrap().delta;
+
+        if let Some(event) = event {
+            if self.name_input.is_some() {
+                self.name_update_input(event);
+            } else {
+                self.update_input(event);
+            }
+        }
+    }
+}
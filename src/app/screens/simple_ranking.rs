@@ -1,6 +1,6 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::event::{self, Event, KeyEventKind};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Layout, Margin, Rect},
@@ -14,9 +14,11 @@ use ratatui::{
 
 use crate::{
     app::{
-        structs::Player,
+        gameplay::{BOARD_SIZES, DEFAULT_BOARD_SIZE, GameMode, daily_seed, replay::replay_path},
+        keymap::{self, Action, Screen},
+        structs::{Leaderboard, Player},
         time::TIME,
-        utils::{fade_in, format_datetime},
+        utils::{day_start, fade_in, format_date_short, format_datetime, today},
     },
     data_manager,
 };
@@ -29,20 +31,34 @@ const ITEM_HEIGHT: usize = 1;
 pub struct RankingActivity {
     itoa_buffer: itoa::Buffer,
     save: Player,
+    players: Vec<Player>,
     players_requested: bool,
+    board_size: usize,
+    /// 每种模式各有一张排行榜
+    mode: GameMode,
+    /// 每日挑战排行榜显示的日期
+    day: i64,
     app_time: Duration,
 
     show_items: Vec<Player>,
+    /// `show_items` 前面参与排名的条目数，之后是当前玩家借助撤销的成绩
+    ranked: usize,
     state: TableState,
     longest_item_lens: (u16, u16, u16),
     scroll_state: ScrollbarState,
 
     pub should_exit: bool,
+    /// 选中行最佳成绩的录像，等待打开
+    pub open_replay: Option<PathBuf>,
 }
 
 impl RankingActivity {
     pub fn new() -> Self {
-        let mut this = Self::default();
+        let mut this = Self {
+            board_size: DEFAULT_BOARD_SIZE,
+            day: today(),
+            ..Default::default()
+        };
         this.state.select(Some(0));
         this
     }
@@ -55,13 +71,89 @@ impl RankingActivity {
 
     pub fn set_save(&mut self, save: Player) {
         self.save = save;
-        self.show_items.clear();
-        self.show_items.push(self.save.clone());
+        self.request_players();
+    }
+
+    pub fn set_board_size(&mut self, board_size: usize) {
+        self.board_size = board_size;
+        self.request_players();
+    }
+
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        self.request_players();
+    }
+
+    /// 每日挑战排行榜切换到前一天或后一天，不会超过今天
+    fn switch_day(&mut self, forward: bool) {
+        self.day = if forward {
+            (self.day + 1).min(today())
+        } else {
+            self.day - 1
+        };
+        self.request_players();
+    }
+
+    /// 当前显示的排行榜
+    fn leaderboard(&self) -> Leaderboard {
+        match self.mode {
+            GameMode::Daily => Leaderboard::Daily {
+                seed: daily_seed(self.day),
+            },
+            mode => Leaderboard::Board {
+                mode,
+                board_size: self.board_size,
+            },
+        }
+    }
+
+    /// 排行榜变了，重新向数据层查询其他玩家的成绩
+    fn request_players(&mut self) {
+        self.players.clear();
         self.players_requested = false;
+        self.refresh_items();
+    }
+
+    fn switch_board_size(&mut self, forward: bool) {
+        let index = BOARD_SIZES
+            .iter()
+            .position(|x| *x == self.board_size)
+            .unwrap_or_default();
+        let index = if forward {
+            (index + 1) % BOARD_SIZES.len()
+        } else {
+            (index + BOARD_SIZES.len() - 1) % BOARD_SIZES.len()
+        };
+        self.set_board_size(BOARD_SIZES[index]);
+    }
+
+    /// 按当前棋盘大小和模式重新生成排行榜条目，借助撤销的记录不参与排行，
+    /// 其他玩家的成绩由数据层按排行榜查询
+    fn refresh_items(&mut self) {
+        let leaderboard = self.leaderboard();
+        let mut items = vec![
+            self.save
+                .filter_records(|r| leaderboard.contains(r) && !r.is_assisted()),
+        ];
+        items.extend(self.players.iter().cloned());
+        self.ranked = items.len();
+        // 当前玩家借助撤销的成绩更好时单独列在最后，不给出名次
+        let assisted = self
+            .save
+            .filter_records(|r| leaderboard.contains(r) && r.is_assisted());
+        if !assisted.records.is_empty() && assisted > items[0] {
+            items.push(assisted);
+        }
+        self.show_items = items;
+        self.constrant_len();
+        self.scroll_state = self
+            .scroll_state
+            .content_length(self.show_items.len() * ITEM_HEIGHT);
+        self.by_score();
     }
 
     pub fn by_score(&mut self) {
-        self.show_items.sort_by(|a, b| b.cmp(a));
+        self.show_items[..self.ranked].sort_by(|a, b| b.cmp(a));
         self.set_row(
             self.show_items
                 .iter()
@@ -142,23 +234,30 @@ impl RankingActivity {
             return;
         };
 
-        if matches!(key.code, KeyCode::Char('q')) || matches!(key.code, KeyCode::Esc) {
+        let action = keymap::action(Screen::Ranking, &key);
+        if action == Some(Action::Quit) {
             self.should_exit = true;
         }
 
         if key.kind != KeyEventKind::Press {
             return;
         }
-        let ctrl_pressed = key.modifiers.contains(KeyModifiers::CONTROL);
-        match key.code {
-            KeyCode::Up => {
-                if ctrl_pressed {
-                    self.reset_row()
-                } else {
-                    self.prev_row()
+        match action {
+            Some(Action::Top) => self.reset_row(),
+            Some(Action::Up) => self.prev_row(),
+            Some(Action::Down) => self.next_row(),
+            Some(Action::Left) if self.mode == GameMode::Daily => self.switch_day(false),
+            Some(Action::Right) if self.mode == GameMode::Daily => self.switch_day(true),
+            Some(Action::Left) => self.switch_board_size(false),
+            Some(Action::Right) => self.switch_board_size(true),
+            Some(Action::Mode) => self.set_mode(self.mode.cycle(&GameMode::RANKED, true)),
+            Some(Action::Confirm) => {
+                if let Some(player) = self.state.selected().and_then(|x| self.show_items.get(x))
+                    && player.best_timestamp != 0
+                {
+                    self.open_replay = Some(replay_path(player.id, player.best_timestamp));
                 }
             }
-            KeyCode::Down => self.next_row(),
             _ => (),
         }
     }
@@ -175,32 +274,57 @@ impl RankingActivity {
     }
 
     pub fn render_footer(&mut self, frame: &mut Frame<'_>, area: Rect) {
-        let header = Paragraph::new("(Q) 退出 | (↓) 向下移动 | (↑) 向上移动 | (Ctrl + ↑) 回到顶部")
-            .fg(tailwind::INDIGO.c100)
-            .block(
-                Block::bordered()
-                    .border_type(BorderType::Rounded)
-                    .fg(tailwind::INDIGO.c300),
-            )
-            .alignment(Alignment::Center);
+        let key = |action| keymap::label(Screen::Ranking, action);
+        let switch = match self.mode {
+            GameMode::Daily => format!("日期 {}", format_date_short(day_start(self.day))),
+            _ => format!("棋盘 {}×{}", self.board_size, self.board_size),
+        };
+        let header = Paragraph::new(format!(
+            "({}) 退出 | ({}) 向下移动 | ({}) 向上移动 | ({}) 回到顶部 | ({}) 观看录像 | ({} {}) {switch} | ({}) 模式 {}",
+            key(Action::Quit),
+            key(Action::Down),
+            key(Action::Up),
+            key(Action::Top),
+            key(Action::Confirm),
+            key(Action::Left),
+            key(Action::Right),
+            key(Action::Mode),
+            self.mode.name()
+        ))
+        .fg(tailwind::INDIGO.c100)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .fg(tailwind::INDIGO.c300),
+        )
+        .alignment(Alignment::Center);
         frame.render_widget(header, area);
     }
 
     pub fn render_table(&mut self, frame: &mut Frame<'_>, area: Rect) {
-        let header = ["排名", "名称", "分数", "所用时间", "达成时间"]
-            .into_iter()
-            .map(Cell::from)
-            .collect::<Row>()
-            .style(
-                Style::default()
-                    .fg(tailwind::INDIGO.c50)
-                    .bg(tailwind::INDIGO.c700),
-            )
-            .height(1);
+        let header = [
+            "排名",
+            "名称",
+            "分数",
+            "最大地块",
+            "步数",
+            "所用时间",
+            "达成时间",
+        ]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .style(
+            Style::default()
+                .fg(tailwind::INDIGO.c50)
+                .bg(tailwind::INDIGO.c700),
+        )
+        .height(1);
         let rows = self.show_items.iter().enumerate().map(|(i, data)| {
             let bg = Color::Reset;
 
             let fg = match i {
+                _ if i >= self.ranked => tailwind::INDIGO.c300,
                 0 => tailwind::AMBER.c200,
                 1 => tailwind::NEUTRAL.c300,
                 2 => tailwind::YELLOW.c500,
@@ -208,9 +332,25 @@ impl RankingActivity {
             };
 
             [
-                Cell::from(format!("#{}", i + 1)),
+                Cell::from(if i < self.ranked {
+                    format!("#{}", i + 1)
+                } else {
+                    String::from("-")
+                }),
                 Cell::from(data.name.as_str()),
-                Cell::from(self.itoa_buffer.format(data.best_score).to_string()),
+                Cell::from(if data.best_record().is_some_and(|x| x.is_assisted()) {
+                    format!("{} ↶", self.itoa_buffer.format(data.best_score))
+                } else {
+                    self.itoa_buffer.format(data.best_score).to_string()
+                }),
+                Cell::from(match data.best_record().map_or(0, |x| x.max_tile) {
+                    0 => String::from("-"),
+                    tile => self.itoa_buffer.format(tile).to_string(),
+                }),
+                Cell::from(match data.best_record().map_or(0, |x| x.moves) {
+                    0 => String::from("-"),
+                    moves => self.itoa_buffer.format(moves).to_string(),
+                }),
                 Cell::from(self.itoa_buffer.format(data.best_time).to_string()),
                 Cell::from(if data.best_timestamp != 0 {
                     format_datetime(data.best_timestamp)
@@ -230,6 +370,8 @@ impl RankingActivity {
                 Constraint::Min(7),
                 Constraint::Min(self.longest_item_lens.0 + 1),
                 Constraint::Min(self.longest_item_lens.1 + 1),
+                Constraint::Min(9),
+                Constraint::Min(6),
                 Constraint::Min(self.longest_item_lens.2 + 1),
                 Constraint::Min(22),
             ],
@@ -271,16 +413,13 @@ impl Activity for RankingActivity {
             self.app_time += time.delta;
         }
 
-        if !self.players_requested {
-            if let Some(players) = data_manager!(get_players_best_except_self) {
-                self.players_requested = true;
-                self.constrant_len();
-                self.show_items.extend(players);
-                self.scroll_state = self
-                    .scroll_state
-                    .content_length(self.show_items.len() * ITEM_HEIGHT);
-                self.by_score();
-            }
+        if !self.players_requested
+            && let Some(players) =
+                data_manager!(get_leaderboard, self.leaderboard()).map(Result::unwrap_or_default)
+        {
+            self.players_requested = true;
+            self.players = players;
+            self.refresh_items();
         }
 
         if let Some(event) = event {
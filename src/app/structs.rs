@@ -1,4 +1,6 @@
-use std::{ops::Mul, time::Duration};
+use std::time::Duration;
+
+use super::gameplay::{DEFAULT_BOARD_SIZE, GameMode};
 
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Player {
@@ -15,6 +17,108 @@ pub struct PlayerRecord {
     pub score: i32,
     pub time: i64,
     pub timestamp: i64,
+    /// 棋盘边长，旧存档中为 0
+    pub board_size: i32,
+    /// 对局种子，按位存放的 `u64`
+    pub seed: i64,
+    /// 本局使用撤销的次数，不为 0 的记录不参与排行
+    pub undo_count: i32,
+    /// 是否合成了目标地块
+    pub won: bool,
+    /// 本局合成的最大地块，旧存档中为 0
+    pub max_tile: i32,
+    /// 有效移动的总次数，以下统计在旧存档中均为 0
+    pub moves: i32,
+    /// 地块合并的次数
+    pub merges: i32,
+    pub moves_up: i32,
+    pub moves_down: i32,
+    pub moves_left: i32,
+    pub moves_right: i32,
+    /// `GameMode` 的编号
+    pub mode: i32,
+    /// 谜题的编号，由谜题名称和开局局面得到，其他模式为 0
+    pub puzzle: i64,
+}
+
+impl PlayerRecord {
+    pub fn board_size(&self) -> usize {
+        if self.board_size <= 0 {
+            DEFAULT_BOARD_SIZE
+        } else {
+            self.board_size as usize
+        }
+    }
+
+    /// 未知的编号按经典模式处理
+    pub fn mode(&self) -> GameMode {
+        GameMode::from_id(self.mode).unwrap_or_default()
+    }
+
+    /// 是否借助了撤销
+    pub fn is_assisted(&self) -> bool {
+        self.undo_count > 0
+    }
+
+    /// 是否是同一局的记录，删除记录时用来找出对应的那条
+    pub fn same_game(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp && self.score == other.score && self.seed == other.seed
+    }
+}
+
+/// 一张排行榜，每日挑战按当天的种子区分，其他模式按棋盘大小区分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Leaderboard {
+    Board { mode: GameMode, board_size: usize },
+    Daily { seed: u64 },
+}
+
+impl Leaderboard {
+    /// 记录是否属于这张排行榜，不考虑是否借助了撤销
+    pub fn contains(&self, record: &PlayerRecord) -> bool {
+        match *self {
+            Self::Board { mode, board_size } => {
+                record.mode() == mode && record.board_size() == board_size
+            }
+            Self::Daily { seed } => record.mode() == GameMode::Daily && record.seed as u64 == seed,
+        }
+    }
+}
+
+impl Player {
+    /// 只保留同一个排行榜的记录，排行榜按棋盘大小和游戏模式区分
+    pub fn for_leaderboard(&self, board_size: usize, mode: GameMode) -> Self {
+        self.filter_records(|x| x.board_size() == board_size && x.mode() == mode)
+    }
+
+    /// 只保留满足条件的记录，并据此重新计算最佳成绩
+    pub fn filter_records(&self, f: impl Fn(&PlayerRecord) -> bool) -> Self {
+        let records = self
+            .records
+            .iter()
+            .filter(|x| f(x))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut player = Self {
+            id: self.id,
+            name: self.name.clone(),
+            records,
+            ..Default::default()
+        };
+        if let Some(x) = player.best_record() {
+            (player.best_score, player.best_time, player.best_timestamp) =
+                (x.score, x.time, x.timestamp);
+        }
+        player
+    }
+
+    /// 最佳成绩对应的那条记录
+    ///
+    /// 与排行榜的排序一致：分数最高，同分时用时长的优先，仍然相同时取最后保存的一条。
+    /// SQLite 数据层的排行榜查询也按这个顺序
+    pub fn best_record(&self) -> Option<&PlayerRecord> {
+        self.records.iter().max_by_key(|x| (x.score, x.time))
+    }
 }
 
 impl PartialOrd for Player {
@@ -33,38 +137,43 @@ impl Ord for Player {
     }
 }
 
+/// 棋盘上的一格，保存地块数值的指数，0 表示空格
 #[derive(Clone, Default, PartialEq, Eq, Copy)]
 pub struct Cell {
-    value: u16,
+    exponent: u8,
 }
 
 impl Cell {
-    pub fn new(value: u16) -> Self {
-        Self { value }
+    /// 数值为 `2^exponent` 的地块
+    pub fn new(exponent: u8) -> Self {
+        Self { exponent }
     }
 
-    pub fn get(&self) -> u16 {
-        self.value
+    pub fn exponent(&self) -> u8 {
+        self.exponent
     }
 
-    pub fn set_v(&mut self, value: u16) {
-        self.value = value;
+    /// 地块的数值，超出 `u64` 时取最大值
+    pub fn value(&self) -> u64 {
+        if self.empty() {
+            0
+        } else {
+            1_u64.checked_shl(self.exponent as u32).unwrap_or(u64::MAX)
+        }
     }
 
     pub fn set(&mut self, value: Self) {
-        self.value = value.value;
+        self.exponent = value.exponent;
     }
 
     pub fn empty(&self) -> bool {
-        self.value == 0
+        self.exponent == 0
     }
-}
 
-impl Mul<u16> for Cell {
-    type Output = Self;
-    fn mul(self, rhs: u16) -> Self::Output {
+    /// 两个相同地块合并后的地块
+    pub fn merged(self) -> Self {
         Self {
-            value: self.value * rhs,
+            exponent: self.exponent.saturating_add(1),
         }
     }
 }
@@ -75,7 +184,7 @@ pub struct Vec2 {
     pub y: usize,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CellMotionDirection {
     Up,
     Down,
@@ -111,7 +220,7 @@ impl Ord for CellAnimationType {
 #[derive(Clone, Copy)]
 pub struct AnimationCell {
     pub src: Vec2,
-    pub value: u16,
+    pub cell: Cell,
     pub animation_type: CellAnimationType,
     pub dest: Option<Vec2>,
     pub duration: Duration,
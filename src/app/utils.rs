@@ -1,4 +1,9 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use chrono::TimeZone;
 use ratatui::{Frame, layout::Rect, style::Color};
@@ -14,6 +19,36 @@ pub fn get_time_millis() -> i64 {
         .unwrap()
 }
 
+/// 本地数据目录，找不到系统数据目录时使用当前目录
+pub fn data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("tui2048")
+}
+
+/// 先写入同目录下的临时文件并落盘，再替换原文件，中途崩溃时原文件保持完整
+pub fn write_atomic(path: impl AsRef<Path>, contents: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let parent = path
+        .parent()
+        .filter(|x| !x.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    // 目录也要落盘，重命名才算完成
+    #[cfg(unix)]
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
 pub fn rect_scale(rect: Rect, factor: f32) -> Rect {
     if factor < 0.0 || !factor.is_finite() {
         return rect;
@@ -88,11 +123,11 @@ pub fn fade_in(frame: &mut Frame<'_>, duration: f32, time: f32, seed: Option<u32
                     (b as f32 * progress) as u8,
                 );
             }
-            if let Some(seed) = seed {
-                if hash(col.x as u32 * seed + col.y as u32) + progress <= 0.9 {
-                    cell.set_symbol(" ");
-                    cell.set_bg(Color::Reset);
-                }
+            if let Some(seed) = seed
+                && hash(col.x as u32 * seed + col.y as u32) + progress <= 0.9
+            {
+                cell.set_symbol(" ");
+                cell.set_bg(Color::Reset);
             }
         }
     }
@@ -104,6 +139,26 @@ pub fn format_datetime(time_stamp: i64) -> String {
         .to_string()
 }
 
+/// 一天的毫秒数
+const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+/// 日期按东八区划分，与上面的格式化一致
+const UTC_OFFSET_MILLIS: i64 = 8 * 60 * 60 * 1000;
+
+/// 时间戳所在的日期，从 1970 年 1 月 1 日起的天数
+pub fn day_of(time_stamp: i64) -> i64 {
+    (time_stamp + UTC_OFFSET_MILLIS).div_euclid(DAY_MILLIS)
+}
+
+/// 某一天零点的时间戳
+pub fn day_start(day: i64) -> i64 {
+    day * DAY_MILLIS - UTC_OFFSET_MILLIS
+}
+
+pub fn today() -> i64 {
+    day_of(get_time_millis())
+}
+
 pub fn format_date_short(time_stamp: i64) -> String {
     (chrono::Utc.timestamp_millis_opt(time_stamp).unwrap() + chrono::Duration::hours(8))
         .format("%y/%m/%d")
@@ -3,9 +3,9 @@ use std::{
     thread,
 };
 
-use app::{
-    data::jni::{JniDataManager, Request, RequestBody, Response, ResponseBody},
-    entry::leave,
+use app::data::jni::{
+    CONTRACT_VERSION, JniDataManager, Request, RequestBody, Response, ResponseBody, java_result,
+    require, require_some,
 };
 use jni::{
     JNIEnv, JavaVM,
@@ -14,6 +14,20 @@ use jni::{
 
 mod app;
 
+// 供独立运行的程序、机器人和其他前端使用
+pub use app::{
+    data::{DataError, DataManager},
+    entry::{leave, run_app},
+    gameplay::{
+        GameConfig, GameMode, Grid,
+        game::{DIRECTIONS, Game, GameStats, MoveOutcome},
+        history::{History, Snapshot},
+        spawn::{NoSpawn, SpawnPolicy, StandardSpawn},
+    },
+    structs::{Cell, CellMotionDirection, Leaderboard, Player, PlayerRecord},
+    utils::{data_dir, write_atomic},
+};
+
 fn start_thread(rx: Receiver<Request>, tx: Sender<Response>, vm: JavaVM) {
     thread::spawn(move || {
         let rx = rx;
@@ -32,55 +46,83 @@ fn start_thread(rx: Receiver<Request>, tx: Sender<Response>, vm: JavaVM) {
             .try_into()
             .unwrap();
 
-        loop {
-            let req = rx.recv().unwrap();
-            let rsp = match req.0 {
+        // Java 抛出的异常转换成错误交给前端显示，不再让工作线程崩溃
+        while let Ok((req, seq)) = rx.recv() {
+            let rsp = match req {
                 RequestBody::GetCurrentPlayer => {
-                    let result = app::data::jni::get_current_player(&mut env, &service).unwrap();
-                    ResponseBody::GetCurrentPlayer(result)
+                    let result = app::data::jni::get_current_player(&mut env, &service);
+                    ResponseBody::GetCurrentPlayer(
+                        java_result(&mut env, result)
+                            .and_then(|x| require_some(x, "getCurrentPlayer")),
+                    )
                 }
                 RequestBody::VerifyAccount(username, password) => {
                     let result =
-                        app::data::jni::verify_account(&mut env, &service, username, password)
-                            .unwrap();
-                    ResponseBody::VerifyAccount(result)
+                        app::data::jni::verify_account(&mut env, &service, username, password);
+                    ResponseBody::VerifyAccount(
+                        java_result(&mut env, result)
+                            .and_then(|x| require_some(x, "verifyAccount")),
+                    )
                 }
                 RequestBody::RegisterAccount(username, password) => {
                     let result =
-                        app::data::jni::register_account(&mut env, &service, username, password)
-                            .unwrap();
-                    ResponseBody::RegisterAccount(result)
+                        app::data::jni::register_account(&mut env, &service, username, password);
+                    ResponseBody::RegisterAccount(
+                        java_result(&mut env, result)
+                            .and_then(|x| require_some(x, "registerAccount")),
+                    )
                 }
                 RequestBody::GetPlayersBestExceptSelf => {
-                    let result =
-                        app::data::jni::get_players_best_except_self(&mut env, &service).unwrap();
-                    ResponseBody::GetPlayersBestExceptSelf(result)
+                    let result = app::data::jni::get_players_best_except_self(&mut env, &service);
+                    ResponseBody::GetPlayersBestExceptSelf(java_result(&mut env, result))
                 }
                 RequestBody::GetPlayers => {
-                    let result =
-                        app::data::jni::get_players(&mut env, &service).unwrap();
-                    ResponseBody::GetPlayers(result)
+                    let result = app::data::jni::get_players(&mut env, &service);
+                    ResponseBody::GetPlayers(java_result(&mut env, result))
                 }
                 RequestBody::SaveRecord(player) => {
-                    let result =
-                        app::data::jni::save_record(&mut env, &service, player).unwrap();
-                    ResponseBody::SaveRecord(result)
+                    let result = app::data::jni::save_record(&mut env, &service, player);
+                    ResponseBody::SaveRecord(
+                        java_result(&mut env, result).and_then(|x| require(x, "saveRecord")),
+                    )
                 }
                 RequestBody::FindPlayer(player) => {
-                    let result = app::data::jni::find_player(&mut env, &service, player).unwrap();
-                    ResponseBody::FindPlayer(result)
+                    let result = app::data::jni::find_player(&mut env, &service, player);
+                    ResponseBody::FindPlayer(java_result(&mut env, result))
                 }
                 RequestBody::UpdatePlayer(player) => {
-                    let result = app::data::jni::update_player(&mut env, &service, player).unwrap();
-                    ResponseBody::UpdatePlayer(result)
+                    let result = app::data::jni::update_player(&mut env, &service, player);
+                    ResponseBody::UpdatePlayer(
+                        java_result(&mut env, result).and_then(|x| require(x, "updatePlayer")),
+                    )
                 }
                 RequestBody::RemovePlayer(player) => {
-                    let result = app::data::jni::remove_player(&mut env, &service, player).unwrap();
-                    ResponseBody::RemovePlayer(result)
+                    let result = app::data::jni::remove_player(&mut env, &service, player);
+                    ResponseBody::RemovePlayer(
+                        java_result(&mut env, result).and_then(|x| require(x, "removePlayer")),
+                    )
+                }
+                RequestBody::RemoveRecord(player) => {
+                    let result = app::data::jni::remove_record(&mut env, &service, player);
+                    ResponseBody::RemoveRecord(
+                        java_result(&mut env, result).and_then(|x| require(x, "removeRecord")),
+                    )
+                }
+                RequestBody::LoadGame(player) => {
+                    let result = app::data::jni::load_game(&mut env, &service, player);
+                    ResponseBody::LoadGame(java_result(&mut env, result))
+                }
+                RequestBody::SaveGame(player, game) => {
+                    let result = app::data::jni::save_game(&mut env, &service, player, game);
+                    ResponseBody::SaveGame(
+                        java_result(&mut env, result).and_then(|x| require(x, "saveGame")),
+                    )
                 }
                 RequestBody::Exit => break,
             };
-            tx.send((rsp, req.1)).unwrap();
+            if tx.send((rsp, seq)).is_err() {
+                break;
+            }
         }
     });
 }
@@ -90,12 +132,29 @@ pub extern "system" fn Java_com_smoother_TacticalGrid2048_view_View_startAndJoin
     mut env: JNIEnv<'local>,
     _: JClass<'local>,
 ) {
-    let is_first_launch = {
-        let utils = env
-            .find_class("com/smoother/TacticalGrid2048/GlobalVariables")
+    let utils = env
+        .find_class("com/smoother/TacticalGrid2048/GlobalVariables")
+        .unwrap();
+    // 旧的 Java 端没有这个字段
+    let version = env
+        .get_static_field(&utils, "CONTRACT_VERSION", "I")
+        .and_then(|x| x.i())
+        .ok();
+    if version != Some(CONTRACT_VERSION) {
+        let _ = env.exception_clear();
+        let message = match version {
+            Some(x) => format!("数据接口版本不一致：Java 端为 {x}，前端需要 {CONTRACT_VERSION}"),
+            None => format!("Java 端没有声明数据接口版本，前端需要 {CONTRACT_VERSION}"),
+        };
+        env.throw(("java/lang/IllegalStateException", message))
             .unwrap();
-        env.get_static_field(utils, "isFirstLaunch", "Z").unwrap().z().unwrap()
-    };
+        return;
+    }
+    let is_first_launch = env
+        .get_static_field(&utils, "isFirstLaunch", "Z")
+        .unwrap()
+        .z()
+        .unwrap();
     let vm = env.get_java_vm().unwrap();
 
     let (req_tx, req_rx) = mpsc::channel::<Request>();
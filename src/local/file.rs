@@ -0,0 +1,538 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File, TryLockError},
+    io::{self, ErrorKind},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use tui2048::{DataError, DataManager, Player, PlayerRecord, data_dir, write_atomic};
+
+use super::{Checked, Hashing, check_name, check_password, new_credentials};
+
+/// 玩家数据文件的文件头
+pub const DATA_MAGIC: &str = "T2048D";
+
+/// 玩家数据格式版本，格式变化时递增
+pub const DATA_VERSION: u32 = 2;
+
+/// 其他进程占用数据文件时最多等待这么久，超过后按超时处理
+const LOCK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 一条记录在文件里的字段数，不含开头的玩家编号，版本 1 中没有最后的谜题编号
+const RECORD_FIELDS: usize = 16;
+
+/// 保存在一个玩家数据文件中的账号
+#[derive(Clone)]
+struct Account {
+    player: Player,
+    salt: String,
+    /// 密码的摘要，见 [`hash_password`]
+    hash: String,
+}
+
+/// 独立运行时使用的本地数据层，玩家、密码和记录都保存在数据目录下的一个文件中
+///
+/// 文件为纯文本，第一行是文件头和版本号：
+///
+/// ```text
+/// T2048D 2
+/// next 3
+/// player 1 <salt> <hash> 玩家名
+/// record 1 2048 95 1700000000000 4 81985529216486895 0 1 2048 300 150 80 70 90 60 0 0
+/// ```
+///
+/// `next` 是下一个注册玩家的编号，编号不会重复使用。`player` 依次是编号、盐、
+/// 密码的摘要和玩家名，摘要形如 `pbkdf2-sha256$<迭代次数>$<摘要>`。`record` 依次是
+/// 玩家编号、分数、用时、时间戳、棋盘边长、种子、撤销次数、是否胜利、最大地块、移动次数、合并次数、四个方向的移动次数、模式和谜题编号。
+/// 中途退出的对局按玩家编号单独存放在 `saves` 目录下。每次修改都整体重写文件，
+/// 写入过程中崩溃不会损坏原有的数据。修改前先锁住旁边的 `.lock` 文件并重新读取，
+/// 同时运行的多个实例不会覆盖彼此写入的数据
+pub struct FileDataManager {
+    path: PathBuf,
+    accounts: Vec<Account>,
+    next_id: i32,
+    /// 本次运行中登录的玩家
+    current: Option<i32>,
+    first_launch: bool,
+    verifying: Hashing<Checked>,
+    registering: Hashing<(String, String)>,
+}
+
+impl FileDataManager {
+    /// 打开数据目录下的数据文件，文件不存在时视为第一次启动
+    pub fn open() -> io::Result<Self> {
+        Self::open_at(data_dir().join("players.t2d"))
+    }
+
+    pub fn open_at(path: PathBuf) -> io::Result<Self> {
+        let (accounts, next_id, first_launch) = match fs::read_to_string(&path) {
+            Ok(text) => {
+                let (accounts, next_id) = decode(&text)?;
+                (accounts, next_id, false)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (Vec::new(), 1, true),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            accounts,
+            next_id,
+            current: None,
+            first_launch,
+            verifying: Hashing::default(),
+            registering: Hashing::default(),
+        })
+    }
+
+    fn save(&self) -> Result<(), DataError> {
+        write_atomic(&self.path, &self.encode()).map_err(backend)
+    }
+
+    /// 重新读取数据文件，其他实例可能已经修改过
+    fn reload(&mut self) -> Result<(), DataError> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => (self.accounts, self.next_id) = decode(&text).map_err(backend)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(e) => return Err(backend(e)),
+        }
+        Ok(())
+    }
+
+    /// 锁住数据文件旁边的锁文件，文件关闭时自动释放
+    fn lock(&self) -> Result<File, DataError> {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(".lock");
+        let path = PathBuf::from(path);
+        if let Some(parent) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(backend)?;
+        }
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(backend)?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(file),
+                Err(TryLockError::WouldBlock) if start.elapsed() < LOCK_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(20))
+                }
+                Err(TryLockError::WouldBlock) => return Err(DataError::Timeout),
+                Err(TryLockError::Error(e)) => return Err(backend(e)),
+            }
+        }
+    }
+
+    /// 持有锁时读取最新的数据，修改后写回；失败时内存中的数据保持修改前的样子
+    fn modify<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, DataError>,
+    ) -> Result<T, DataError> {
+        let _lock = self.lock()?;
+        self.reload()?;
+        let (accounts, next_id) = (self.accounts.clone(), self.next_id);
+        let result = match f(self) {
+            Ok(x) => self.save().map(|()| x),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.accounts = accounts;
+            self.next_id = next_id;
+        }
+        result
+    }
+
+    fn encode(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "{DATA_MAGIC} {DATA_VERSION}");
+        let _ = writeln!(text, "next {}", self.next_id);
+        for account in &self.accounts {
+            let player = &account.player;
+            let _ = writeln!(
+                text,
+                "player {} {} {} {}",
+                player.id, account.salt, account.hash, player.name
+            );
+            for x in &player.records {
+                let _ = writeln!(
+                    text,
+                    "record {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+                    player.id,
+                    x.score,
+                    x.time,
+                    x.timestamp,
+                    x.board_size,
+                    x.seed,
+                    x.undo_count,
+                    x.won as i32,
+                    x.max_tile,
+                    x.moves,
+                    x.merges,
+                    x.moves_up,
+                    x.moves_down,
+                    x.moves_left,
+                    x.moves_right,
+                    x.mode,
+                    x.puzzle,
+                );
+            }
+        }
+        text
+    }
+
+    fn account(&self, id: i32) -> Option<&Account> {
+        self.accounts.iter().find(|x| x.player.id == id)
+    }
+
+    fn account_mut(&mut self, id: i32) -> Option<&mut Account> {
+        self.accounts.iter_mut().find(|x| x.player.id == id)
+    }
+
+    fn save_path(&self, id: i32) -> PathBuf {
+        self.path.with_file_name("saves").join(format!("{id}.t2s"))
+    }
+
+    fn players(&self) -> Vec<Player> {
+        self.accounts.iter().map(|x| x.player.clone()).collect()
+    }
+}
+
+impl DataManager for FileDataManager {
+    fn is_first_launch(&mut self) -> bool {
+        self.first_launch
+    }
+
+    fn verify_account(&mut self, username: String, password: String) -> Result<Player, DataError> {
+        self.reload()?;
+        let account = self
+            .accounts
+            .iter()
+            .find(|x| x.player.name == username)
+            .ok_or(DataError::NotFound)?;
+        let player = account.player.clone();
+        let (salt, stored, secret) = (account.salt.clone(), account.hash.clone(), password.clone());
+        let checked = self.verifying.run(&username, &password, move || {
+            check_password(&salt, &secret, &stored)
+        })?;
+        match checked {
+            Checked::Wrong => return Err(DataError::AuthFailed),
+            Checked::Matched => (),
+            Checked::Rehashed(hash) => {
+                // 换成当前参数的摘要，写入失败时保留原来的摘要，下次登录再试
+                let _ = self.modify(|this| {
+                    let account = this.account_mut(player.id).ok_or(DataError::NotFound)?;
+                    account.hash = hash;
+                    Ok(())
+                });
+            }
+        }
+        self.current = Some(player.id);
+        Ok(player)
+    }
+
+    fn register_account(
+        &mut self,
+        username: String,
+        password: String,
+    ) -> Result<Player, DataError> {
+        check_name(&username, "用户名")?;
+        let secret = password.clone();
+        let (salt, hash) = self
+            .registering
+            .run(&username, &password, move || new_credentials(&secret))?;
+        let player = self.modify(|this| {
+            if this.accounts.iter().any(|x| x.player.name == username) {
+                return Err(DataError::Conflict(format!("用户名 {username} 已被占用")));
+            }
+            let player = Player {
+                id: this.next_id,
+                name: username,
+                ..Default::default()
+            };
+            this.accounts.push(Account {
+                player: player.clone(),
+                salt,
+                hash,
+            });
+            this.next_id += 1;
+            Ok(player)
+        })?;
+        self.current = Some(player.id);
+        Ok(player)
+    }
+
+    fn get_current_player(&mut self) -> Result<Player, DataError> {
+        self.reload()?;
+        self.current
+            .and_then(|x| self.account(x))
+            .map(|x| x.player.clone())
+            .ok_or(DataError::NotFound)
+    }
+
+    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, DataError> {
+        self.reload()?;
+        Ok(self
+            .players()
+            .into_iter()
+            .filter(|x| Some(x.id) != self.current)
+            .collect())
+    }
+
+    fn get_players(&mut self) -> Result<Vec<Player>, DataError> {
+        self.reload()?;
+        Ok(self.players())
+    }
+
+    fn save_record(&mut self, player: Player) -> Result<(), DataError> {
+        self.modify(|this| {
+            let account = this.account_mut(player.id).ok_or(DataError::NotFound)?;
+            account.player.records.extend(player.records);
+            account.player = account.player.filter_records(|_| true);
+            Ok(())
+        })
+    }
+
+    /// 按编号或名称中的文字查找，搜索框里的文字放在 `name` 中
+    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, DataError> {
+        self.reload()?;
+        Ok(self
+            .players()
+            .into_iter()
+            .filter(|x| x.name.contains(&player.name) || x.id.to_string() == player.name)
+            .collect())
+    }
+
+    fn update_player(&mut self, player: Player) -> Result<(), DataError> {
+        check_name(&player.name, "玩家名")?;
+        self.modify(|this| {
+            // 改名后不能与其他玩家重名，否则无法登录
+            if this
+                .accounts
+                .iter()
+                .any(|x| x.player.id != player.id && x.player.name == player.name)
+            {
+                return Err(DataError::Conflict(format!(
+                    "已经有名为 {} 的玩家",
+                    player.name
+                )));
+            }
+            let account = this.account_mut(player.id).ok_or(DataError::NotFound)?;
+            account.player.name = player.name;
+            Ok(())
+        })
+    }
+
+    fn remove_record(&mut self, player: Player) -> Result<(), DataError> {
+        self.modify(|this| {
+            let account = this.account_mut(player.id).ok_or(DataError::NotFound)?;
+            for record in &player.records {
+                let records = &mut account.player.records;
+                if let Some(index) = records.iter().position(|x| x.same_game(record)) {
+                    records.remove(index);
+                }
+            }
+            account.player = account.player.filter_records(|_| true);
+            Ok(())
+        })
+    }
+
+    fn remove_player(&mut self, player: Player) -> Result<(), DataError> {
+        self.modify(|this| {
+            let index = this
+                .accounts
+                .iter()
+                .position(|x| x.player.id == player.id)
+                .ok_or(DataError::NotFound)?;
+            this.accounts.remove(index);
+            Ok(())
+        })?;
+        let _ = fs::remove_file(self.save_path(player.id));
+        Ok(())
+    }
+
+    fn load_game(&mut self, player: Player) -> Result<Option<String>, DataError> {
+        match fs::read_to_string(self.save_path(player.id)) {
+            Ok(game) => Ok(Some(game)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(backend(e)),
+        }
+    }
+
+    fn save_game(&mut self, player: Player, game: Option<String>) -> Result<(), DataError> {
+        let path = self.save_path(player.id);
+        let result = match game {
+            Some(game) => write_atomic(path, &game),
+            None => match fs::remove_file(path) {
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+                x => x,
+            },
+        };
+        result.map_err(backend)
+    }
+}
+
+fn backend(e: io::Error) -> DataError {
+    DataError::Backend(e.to_string())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn decode(text: &str) -> io::Result<(Vec<Account>, i32)> {
+    let mut lines = text.lines();
+    let header = lines.next().ok_or_else(|| invalid("empty data file"))?;
+    let Some((DATA_MAGIC, version)) = header.split_once(' ') else {
+        return Err(invalid("not a data file"));
+    };
+    let version = version
+        .trim()
+        .parse::<u32>()
+        .map_err(|_| invalid("bad data version"))?;
+    if version > DATA_VERSION {
+        return Err(invalid(&format!("unsupported data version {version}")));
+    }
+
+    let mut accounts: Vec<Account> = Vec::new();
+    let mut next_id = 1;
+    for line in lines {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "next" => next_id = value.trim().parse().map_err(|_| invalid("bad next id"))?,
+            "player" => {
+                let mut parts = value.splitn(4, ' ');
+                let (Some(id), Some(salt), Some(hash), Some(name)) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err(invalid("bad player"));
+                };
+                accounts.push(Account {
+                    player: Player {
+                        id: id.parse().map_err(|_| invalid("bad player id"))?,
+                        name: name.to_string(),
+                        ..Default::default()
+                    },
+                    salt: salt.to_string(),
+                    hash: hash.to_string(),
+                });
+            }
+            "record" => {
+                let fields = value
+                    .split_whitespace()
+                    .map(|x| x.parse::<i64>().map_err(|_| invalid("bad record")))
+                    .collect::<io::Result<Vec<_>>>()?;
+                let expected = if version < 2 {
+                    RECORD_FIELDS
+                } else {
+                    RECORD_FIELDS + 1
+                };
+                if fields.len() != expected {
+                    return Err(invalid("bad record"));
+                }
+                let account = accounts
+                    .iter_mut()
+                    .find(|x| x.player.id as i64 == fields[0])
+                    .ok_or_else(|| invalid("record of unknown player"))?;
+                let f = |i: usize| {
+                    i32::try_from(fields[i]).map_err(|_| invalid("record field out of range"))
+                };
+                account.player.records.push(PlayerRecord {
+                    score: f(1)?,
+                    time: fields[2],
+                    timestamp: fields[3],
+                    board_size: f(4)?,
+                    seed: fields[5],
+                    undo_count: f(6)?,
+                    won: fields[7] != 0,
+                    max_tile: f(8)?,
+                    moves: f(9)?,
+                    merges: f(10)?,
+                    moves_up: f(11)?,
+                    moves_down: f(12)?,
+                    moves_left: f(13)?,
+                    moves_right: f(14)?,
+                    mode: f(15)?,
+                    puzzle: fields.get(16).copied().unwrap_or_default(),
+                });
+            }
+            // 未来版本新增的字段
+            _ => (),
+        }
+    }
+
+    for account in &mut accounts {
+        account.player = account.player.filter_records(|_| true);
+    }
+    let max_id = accounts.iter().map(|x| x.player.id).max().unwrap_or(0);
+    Ok((accounts, next_id.max(max_id + 1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tui2048-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("players.t2d")
+    }
+
+    fn record(score: i32) -> Player {
+        Player {
+            id: 1,
+            records: vec![PlayerRecord {
+                score,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn instances_keep_each_others_records() {
+        let path = temp_path("merge");
+        write_atomic(&path, "T2048D 2\nnext 2\nplayer 1 salt hash 玩家\n").unwrap();
+        let mut a = FileDataManager::open_at(path.clone()).unwrap();
+        let mut b = FileDataManager::open_at(path.clone()).unwrap();
+        a.save_record(record(100)).unwrap();
+        b.save_record(record(200)).unwrap();
+
+        let mut c = FileDataManager::open_at(path.clone()).unwrap();
+        let player = c.get_players().unwrap().remove(0);
+        let mut scores = player.records.iter().map(|x| x.score).collect::<Vec<_>>();
+        scores.sort();
+        assert_eq!(scores, [100, 200]);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn rejects_out_of_range_fields() {
+        let text = "T2048D 2\nplayer 1 salt hash 玩家\n";
+        let line = "record 1 2048 95 0 4 0 0 1 2048 300 150 80 70 90 60 0 0\n";
+        assert_eq!(decode(&format!("{text}{line}")).unwrap().0.len(), 1);
+        let line = line.replacen("2048", "4294969344", 1);
+        assert!(decode(&format!("{text}{line}")).is_err());
+    }
+
+    #[test]
+    fn rejects_names_with_line_breaks() {
+        let path = temp_path("names");
+        write_atomic(&path, "T2048D 2\nnext 2\nplayer 1 salt hash 玩家\n").unwrap();
+        let mut data = FileDataManager::open_at(path.clone()).unwrap();
+        let forged = Player {
+            id: 1,
+            name: String::from("玩家\nplayer 2 salt hash 伪造"),
+            ..Default::default()
+        };
+        let result = data.update_player(forged);
+        assert!(matches!(result, Err(DataError::Validation(_))));
+        let result = data.register_account(String::from("a\rb"), String::new());
+        assert!(matches!(result, Err(DataError::Validation(_))));
+        assert_eq!(data.get_players().unwrap().len(), 1);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
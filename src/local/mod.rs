@@ -0,0 +1,210 @@
+//! 独立运行时使用的本地数据层，只编译进可执行文件，Java 端加载的动态库里没有这些代码
+
+use std::{
+    fmt::Write as _,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tui2048::DataError;
+
+pub mod file;
+pub mod sqlite;
+
+/// 检查用户名或玩家名，`what` 是提示里的称呼
+///
+/// 数据文件一行一条，名字里的换行会变成多出来的行，所以两种数据层都不接受控制字符
+fn check_name(name: &str, what: &str) -> Result<(), DataError> {
+    if name.trim().is_empty() {
+        return Err(DataError::Validation(format!("{what}不能为空")));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(DataError::Validation(format!(
+            "{what}不能包含换行等控制字符"
+        )));
+    }
+    Ok(())
+}
+
+/// 本地数据层为每个账号生成的随机盐
+fn new_salt() -> String {
+    to_hex(&rand::thread_rng().r#gen::<[u8; 16]>())
+}
+
+/// 在后台线程计算密码摘要的任务，按用户名和密码区分
+///
+/// PBKDF2 要迭代几十万次，在界面线程上计算会让终端卡住。算完之前返回 `Empty`，
+/// 界面会像等待 JNI 数据层的结果一样，下一帧用同样的参数再来取
+struct Hashing<T> {
+    job: Option<((String, String), Receiver<T>)>,
+}
+
+impl<T> Default for Hashing<T> {
+    fn default() -> Self {
+        Self { job: None }
+    }
+}
+
+impl<T: Send + 'static> Hashing<T> {
+    /// 用户名和密码与进行中的任务相同时取回结果，否则丢下旧的任务，开始新的计算
+    fn run(
+        &mut self,
+        username: &str,
+        password: &str,
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, DataError> {
+        if let Some(((u, p), rx)) = &self.job
+            && u == username
+            && p == password
+        {
+            let result = match rx.try_recv() {
+                Ok(x) => Ok(x),
+                Err(TryRecvError::Empty) => return Err(DataError::Empty),
+                Err(TryRecvError::Disconnected) => Err(DataError::Backend(String::from(
+                    "计算密码摘要的线程意外退出",
+                ))),
+            };
+            self.job = None;
+            return result;
+        }
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = tx.send(f());
+        });
+        self.job = Some(((username.to_owned(), password.to_owned()), rx));
+        Err(DataError::Empty)
+    }
+}
+
+/// 验证密码的结果
+enum Checked {
+    Wrong,
+    Matched,
+    /// 密码正确，摘要换成了按当前参数计算的这个
+    Rehashed(String),
+}
+
+/// 验证密码，摘要的参数过时时顺带算出新的摘要，盐保持不变
+fn check_password(salt: &str, password: &str, stored: &str) -> Checked {
+    if !verify_password(salt, password, stored) {
+        Checked::Wrong
+    } else if needs_rehash(stored) {
+        Checked::Rehashed(hash_password(salt, password))
+    } else {
+        Checked::Matched
+    }
+}
+
+/// 为新账号生成盐并计算摘要
+fn new_credentials(password: &str) -> (String, String) {
+    let salt = new_salt();
+    let hash = hash_password(&salt, password);
+    (salt, hash)
+}
+
+/// 新摘要使用的 PBKDF2 迭代次数，调整后旧的摘要仍按其中记下的次数验证
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// 摘要的前缀，后面依次是迭代次数和摘要本身，用 `$` 分隔
+const PBKDF2_PREFIX: &str = "pbkdf2-sha256";
+
+/// 密码的摘要，本地数据层只保存这个摘要，参数和摘要写在一起
+fn hash_password(salt: &str, password: &str) -> String {
+    pbkdf2_hash(salt, password, PBKDF2_ROUNDS)
+}
+
+/// 检查密码与保存的摘要是否一致，早期版本保存的单次 SHA-256 摘要也能验证
+fn verify_password(salt: &str, password: &str, stored: &str) -> bool {
+    let expected = match stored.split('$').collect::<Vec<_>>()[..] {
+        [PBKDF2_PREFIX, rounds, _] => match rounds.parse() {
+            Ok(rounds) if rounds > 0 => pbkdf2_hash(salt, password, rounds),
+            _ => return false,
+        },
+        [_] => {
+            let mut hasher = Sha256::new();
+            hasher.update(salt.as_bytes());
+            hasher.update(password.as_bytes());
+            to_hex(&hasher.finalize())
+        }
+        _ => return false,
+    };
+    // 逐字节比较全部内容，耗时不随第一个不同的位置变化
+    expected.len() == stored.len()
+        && expected
+            .bytes()
+            .zip(stored.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// 摘要不是按当前参数计算的，验证通过后应当重新计算并保存
+fn needs_rehash(stored: &str) -> bool {
+    !stored.starts_with(&format!("{PBKDF2_PREFIX}${PBKDF2_ROUNDS}$"))
+}
+
+fn pbkdf2_hash(salt: &str, password: &str, rounds: u32) -> String {
+    let mut key = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), rounds, &mut key);
+    format!("{PBKDF2_PREFIX}${rounds}${}", to_hex(&key))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut text, x| {
+        let _ = write!(text, "{x:02x}");
+        text
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_round_trip() {
+        // 测试用较少的迭代次数，未优化的构建里完整的迭代要好几秒
+        let hash = pbkdf2_hash("salt", "密码", 1000);
+        assert!(hash.starts_with("pbkdf2-sha256$1000$"));
+        assert!(verify_password("salt", "密码", &hash));
+        assert!(!verify_password("salt", "密", &hash));
+        assert!(!verify_password("pepper", "密码", &hash));
+        assert!(!verify_password("salt", "密码", "pbkdf2-sha256$0$00"));
+        assert!(!verify_password("salt", "密码", "pbkdf2-sha256$x$00"));
+    }
+
+    #[test]
+    fn legacy_sha256_still_verifies() {
+        let mut hasher = Sha256::new();
+        hasher.update(b"salt");
+        hasher.update("密码".as_bytes());
+        let legacy = to_hex(&hasher.finalize());
+        assert!(verify_password("salt", "密码", &legacy));
+        assert!(!verify_password("salt", "x", &legacy));
+        assert!(needs_rehash(&legacy));
+    }
+
+    #[test]
+    fn outdated_rounds_need_rehash() {
+        assert!(needs_rehash(&pbkdf2_hash("salt", "密码", 1000)));
+        assert!(!needs_rehash(&format!(
+            "{PBKDF2_PREFIX}${PBKDF2_ROUNDS}$00"
+        )));
+    }
+
+    #[test]
+    fn hashing_runs_in_the_background() {
+        let mut hashing = Hashing::default();
+        assert_eq!(hashing.run("甲", "a", || 1), Err(DataError::Empty));
+        // 换了密码，之前的任务作废
+        assert_eq!(hashing.run("甲", "b", || 2), Err(DataError::Empty));
+        let result = loop {
+            match hashing.run("甲", "b", || 3) {
+                Err(DataError::Empty) => thread::sleep(std::time::Duration::from_millis(1)),
+                result => break result,
+            }
+        };
+        assert_eq!(result, Ok(2));
+        assert!(hashing.job.is_none());
+    }
+}
@@ -0,0 +1,697 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use rusqlite::{Connection, ErrorCode, OptionalExtension, Row, TransactionBehavior, params};
+
+use tui2048::{DataError, DataManager, GameMode, Leaderboard, Player, PlayerRecord, data_dir};
+
+use super::{Checked, Hashing, check_name, check_password, new_credentials};
+
+/// 其他进程占用数据库时最多等待这么久，超过后按超时处理
+const BUSY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 按顺序执行的数据库迁移，执行到第几条记在 `user_version` 中，只能在末尾追加
+const MIGRATIONS: [&str; 1] = [indoc::indoc! {"
+        CREATE TABLE players (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE credentials (
+            player_id INTEGER PRIMARY KEY REFERENCES players(id) ON DELETE CASCADE,
+            salt TEXT NOT NULL,
+            hash TEXT NOT NULL
+        );
+        CREATE TABLE records (
+            id INTEGER PRIMARY KEY,
+            player_id INTEGER NOT NULL REFERENCES players(id) ON DELETE CASCADE,
+            score INTEGER NOT NULL,
+            time INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            board_size INTEGER NOT NULL,
+            seed INTEGER NOT NULL,
+            undo_count INTEGER NOT NULL,
+            won INTEGER NOT NULL,
+            max_tile INTEGER NOT NULL,
+            moves INTEGER NOT NULL,
+            merges INTEGER NOT NULL,
+            moves_up INTEGER NOT NULL,
+            moves_down INTEGER NOT NULL,
+            moves_left INTEGER NOT NULL,
+            moves_right INTEGER NOT NULL,
+            mode INTEGER NOT NULL,
+            puzzle INTEGER NOT NULL
+        );
+        -- 每个玩家的最佳成绩
+        CREATE INDEX records_best ON records(player_id, score DESC, time DESC);
+        -- 按模式和棋盘大小区分的排行榜
+        CREATE INDEX records_leaderboard ON records(mode, board_size, score DESC, time DESC);
+        CREATE TABLE saved_games (
+            player_id INTEGER PRIMARY KEY REFERENCES players(id) ON DELETE CASCADE,
+            game TEXT NOT NULL
+        );
+    "}];
+
+/// 玩家和各自最佳成绩的查询，最佳成绩的选法与 `Player::best_record` 一致
+const PLAYERS_QUERY: &str = indoc::indoc! {"
+    SELECT p.id, p.name, COALESCE(b.score, 0), COALESCE(b.time, 0), COALESCE(b.timestamp, 0)
+    FROM players p
+    LEFT JOIN records b ON b.id = (
+        SELECT r.id FROM records r WHERE r.player_id = p.id
+        ORDER BY r.score DESC, r.time DESC, r.id DESC LIMIT 1
+    )
+"};
+
+/// 排行榜查询，用 `records_leaderboard` 索引找出一张榜上的记录，每个玩家只取最好的一条，
+/// 选法和排序与默认的 `DataManager::get_leaderboard` 一致
+const LEADERBOARD_QUERY: &str = indoc::indoc! {"
+    SELECT {columns}, p.id, p.name FROM (
+        SELECT r.*, ROW_NUMBER() OVER (
+            PARTITION BY r.player_id ORDER BY r.score DESC, r.time DESC, r.id DESC
+        ) AS n
+        FROM records r
+        WHERE r.mode = ?1 AND {filter} AND r.undo_count = 0
+    ) JOIN players p ON p.id = player_id
+    WHERE n = 1 AND p.id != ?3
+    ORDER BY score DESC, time DESC, p.id
+"};
+
+const RECORD_COLUMNS: &str = "score, time, timestamp, board_size, seed, undo_count, won, \
+    max_tile, moves, merges, moves_up, moves_down, moves_left, moves_right, mode, puzzle";
+
+/// `RECORD_COLUMNS` 的列数，查询中排在记录之后的列从这里开始编号
+const RECORD_COLUMN_COUNT: usize = 16;
+
+/// 保存在 SQLite 数据库中的本地数据层，多个进程可以同时使用同一个数据库
+pub struct SqliteDataManager {
+    conn: Connection,
+    /// 本次运行中登录的玩家
+    current: Option<i32>,
+    first_launch: bool,
+    verifying: Hashing<Checked>,
+    registering: Hashing<(String, String)>,
+}
+
+impl SqliteDataManager {
+    /// 打开数据目录下的数据库，数据库不存在时视为第一次启动
+    pub fn open() -> io::Result<Self> {
+        Self::open_at(data_dir().join("tui2048.db"))
+    }
+
+    pub fn open_at(path: PathBuf) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let first_launch = !path.exists();
+        let mut conn = Connection::open(&path).map_err(io::Error::other)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(io::Error::other)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(io::Error::other)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn,
+            current: None,
+            first_launch,
+            verifying: Hashing::default(),
+            registering: Hashing::default(),
+        })
+    }
+
+    fn query_players(
+        &self,
+        filter: &str,
+        order: &str,
+        params: impl rusqlite::Params,
+    ) -> rusqlite::Result<Vec<Player>> {
+        let mut statement = self
+            .conn
+            .prepare_cached(&format!("{PLAYERS_QUERY} {filter} ORDER BY {order}"))?;
+        let mut players = statement
+            .query_map(params, |row| {
+                Ok(Player {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    best_score: row.get(2)?,
+                    best_time: row.get(3)?,
+                    best_timestamp: row.get(4)?,
+                    records: Vec::new(),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for player in &mut players {
+            player.records = self.query_records(player.id)?;
+        }
+        Ok(players)
+    }
+
+    fn query_records(&self, player_id: i32) -> rusqlite::Result<Vec<PlayerRecord>> {
+        let mut statement = self.conn.prepare_cached(&format!(
+            "SELECT {RECORD_COLUMNS} FROM records WHERE player_id = ?1 ORDER BY id"
+        ))?;
+        statement.query_map([player_id], record_from_row)?.collect()
+    }
+
+    fn query_player(&self, id: i32) -> rusqlite::Result<Option<Player>> {
+        Ok(self.query_players("WHERE p.id = ?1", "p.id", [id])?.pop())
+    }
+
+    fn register(&mut self, username: &str, password: &str) -> Result<Player, DataError> {
+        let secret = password.to_owned();
+        let (salt, hash) = self
+            .registering
+            .run(username, password, move || new_credentials(&secret))?;
+        let tx = self.conn.transaction().map_err(db_error)?;
+        let inserted = tx
+            .execute(
+                "INSERT INTO players (name) VALUES (?1) ON CONFLICT (name) DO NOTHING",
+                [username],
+            )
+            .map_err(db_error)?;
+        if inserted == 0 {
+            return Err(DataError::Conflict(format!("用户名 {username} 已被占用")));
+        }
+        let id = tx.last_insert_rowid() as i32;
+        tx.execute(
+            "INSERT INTO credentials (player_id, salt, hash) VALUES (?1, ?2, ?3)",
+            params![id, salt, hash],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+        self.current = Some(id);
+        self.query_player(id)
+            .map_err(db_error)?
+            .ok_or(DataError::NotFound)
+    }
+
+    fn verify(&mut self, username: &str, password: &str) -> Result<Player, DataError> {
+        let (id, salt, hash) = self
+            .conn
+            .query_row(
+                "SELECT p.id, c.salt, c.hash FROM players p \
+                 JOIN credentials c ON c.player_id = p.id WHERE p.name = ?1",
+                [username],
+                |row| {
+                    Ok((
+                        row.get::<_, i32>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(db_error)?
+            .ok_or(DataError::NotFound)?;
+        let secret = password.to_owned();
+        let checked = self.verifying.run(username, password, move || {
+            check_password(&salt, &secret, &hash)
+        })?;
+        match checked {
+            Checked::Wrong => return Err(DataError::AuthFailed),
+            Checked::Matched => (),
+            Checked::Rehashed(hash) => {
+                // 换成当前参数的摘要，写入失败时保留原来的摘要，下次登录再试
+                let _ = self.conn.execute(
+                    "UPDATE credentials SET hash = ?1 WHERE player_id = ?2",
+                    params![hash, id],
+                );
+            }
+        }
+        self.current = Some(id);
+        self.query_player(id)
+            .map_err(db_error)?
+            .ok_or(DataError::NotFound)
+    }
+
+    fn insert_records(&mut self, player: &Player) -> Result<(), DataError> {
+        let tx = self.conn.transaction().map_err(db_error)?;
+        if !player_exists(&tx, player.id).map_err(db_error)? {
+            return Err(DataError::NotFound);
+        }
+        {
+            let mut statement = tx.prepare_cached(&format!(
+                "INSERT INTO records (player_id, {RECORD_COLUMNS}) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)"
+            )).map_err(db_error)?;
+            for x in &player.records {
+                statement
+                    .execute(params![
+                        player.id,
+                        x.score,
+                        x.time,
+                        x.timestamp,
+                        // 没有棋盘边长的记录按默认大小写入，排行榜查询可以直接比较
+                        x.board_size() as i32,
+                        x.seed,
+                        x.undo_count,
+                        x.won,
+                        x.max_tile,
+                        x.moves,
+                        x.merges,
+                        x.moves_up,
+                        x.moves_down,
+                        x.moves_left,
+                        x.moves_right,
+                        x.mode,
+                        x.puzzle,
+                    ])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(())
+    }
+}
+
+impl DataManager for SqliteDataManager {
+    fn is_first_launch(&mut self) -> bool {
+        self.first_launch
+    }
+
+    fn verify_account(&mut self, username: String, password: String) -> Result<Player, DataError> {
+        self.verify(&username, &password)
+    }
+
+    fn register_account(
+        &mut self,
+        username: String,
+        password: String,
+    ) -> Result<Player, DataError> {
+        check_name(&username, "用户名")?;
+        self.register(&username, &password)
+    }
+
+    fn get_current_player(&mut self) -> Result<Player, DataError> {
+        let Some(id) = self.current else {
+            return Err(DataError::NotFound);
+        };
+        self.query_player(id)
+            .map_err(db_error)?
+            .ok_or(DataError::NotFound)
+    }
+
+    fn get_players_best_except_self(&mut self) -> Result<Vec<Player>, DataError> {
+        self.query_players(
+            "WHERE p.id != ?1",
+            "b.score DESC, b.time DESC, p.id",
+            [self.current.unwrap_or_default()],
+        )
+        .map_err(db_error)
+    }
+
+    fn get_players(&mut self) -> Result<Vec<Player>, DataError> {
+        self.query_players("", "p.id", ()).map_err(db_error)
+    }
+
+    fn get_leaderboard(&mut self, leaderboard: Leaderboard) -> Result<Vec<Player>, DataError> {
+        let (mode, filter, key) = match leaderboard {
+            Leaderboard::Board { mode, board_size } => {
+                (mode, "r.board_size = ?2", board_size as i64)
+            }
+            Leaderboard::Daily { seed } => (GameMode::Daily, "r.seed = ?2", seed as i64),
+        };
+        let query = LEADERBOARD_QUERY
+            .replace("{columns}", RECORD_COLUMNS)
+            .replace("{filter}", filter);
+        let mut statement = self.conn.prepare_cached(&query).map_err(db_error)?;
+        let players = statement
+            .query_map(
+                params![mode.id(), key, self.current.unwrap_or_default()],
+                |row| {
+                    let record = record_from_row(row)?;
+                    Ok(Player {
+                        id: row.get(RECORD_COLUMN_COUNT)?,
+                        name: row.get(RECORD_COLUMN_COUNT + 1)?,
+                        best_score: record.score,
+                        best_time: record.time,
+                        best_timestamp: record.timestamp,
+                        records: vec![record],
+                    })
+                },
+            )
+            .map_err(db_error)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(db_error)?;
+        Ok(players)
+    }
+
+    fn save_record(&mut self, player: Player) -> Result<(), DataError> {
+        self.insert_records(&player)
+    }
+
+    /// 按编号或名称中的文字查找，搜索框里的文字放在 `name` 中
+    fn find_player(&mut self, player: Player) -> Result<Vec<Player>, DataError> {
+        self.query_players(
+            "WHERE instr(p.name, ?1) > 0 OR CAST(p.id AS TEXT) = ?1",
+            "p.id",
+            [player.name],
+        )
+        .map_err(db_error)
+    }
+
+    fn update_player(&mut self, player: Player) -> Result<(), DataError> {
+        check_name(&player.name, "玩家名")?;
+        let tx = self.conn.transaction().map_err(db_error)?;
+        let renamed = tx
+            .execute(
+                "UPDATE players SET name = ?2 WHERE id = ?1 \
+             AND NOT EXISTS (SELECT 1 FROM players WHERE name = ?2 AND id != ?1)",
+                params![player.id, player.name],
+            )
+            .map_err(db_error)?;
+        if renamed == 0 {
+            if !player_exists(&tx, player.id).map_err(db_error)? {
+                return Err(DataError::NotFound);
+            }
+            // 改名后不能与其他玩家重名，否则无法登录
+            return Err(DataError::Conflict(format!(
+                "已经有名为 {} 的玩家",
+                player.name
+            )));
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_record(&mut self, player: Player) -> Result<(), DataError> {
+        let tx = self.conn.transaction().map_err(db_error)?;
+        if !player_exists(&tx, player.id).map_err(db_error)? {
+            return Err(DataError::NotFound);
+        }
+        {
+            // 同一局只删一条，其他进程写入的记录不受影响
+            let mut statement = tx
+                .prepare_cached(
+                    "DELETE FROM records WHERE id = (SELECT id FROM records \
+                 WHERE player_id = ?1 AND timestamp = ?2 AND score = ?3 AND seed = ?4 LIMIT 1)",
+                )
+                .map_err(db_error)?;
+            for x in &player.records {
+                statement
+                    .execute(params![player.id, x.timestamp, x.score, x.seed])
+                    .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+        Ok(())
+    }
+
+    fn remove_player(&mut self, player: Player) -> Result<(), DataError> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM players WHERE id = ?1", [player.id])
+            .map_err(db_error)?;
+        if removed == 0 {
+            return Err(DataError::NotFound);
+        }
+        Ok(())
+    }
+
+    fn load_game(&mut self, player: Player) -> Result<Option<String>, DataError> {
+        self.conn
+            .query_row(
+                "SELECT game FROM saved_games WHERE player_id = ?1",
+                [player.id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)
+    }
+
+    fn save_game(&mut self, player: Player, game: Option<String>) -> Result<(), DataError> {
+        match game {
+            Some(game) => self
+                .conn
+                .execute(
+                    "INSERT INTO saved_games (player_id, game) VALUES (?1, ?2) \
+                 ON CONFLICT (player_id) DO UPDATE SET game = excluded.game",
+                    params![player.id, game],
+                )
+                .map_err(db_error)?,
+            None => self
+                .conn
+                .execute("DELETE FROM saved_games WHERE player_id = ?1", [player.id])
+                .map_err(db_error)?,
+        };
+        Ok(())
+    }
+}
+
+/// 把数据库升级到最新的结构，多个进程同时启动时只有一个会执行迁移
+fn migrate(conn: &mut Connection) -> io::Result<()> {
+    loop {
+        let tx = conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(io::Error::other)?;
+        let version = tx
+            .pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0))
+            .map_err(io::Error::other)?;
+        let version = version as usize;
+        if version > MIGRATIONS.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported database version {version}"),
+            ));
+        }
+        let Some(migration) = MIGRATIONS.get(version) else {
+            return Ok(());
+        };
+        tx.execute_batch(migration).map_err(io::Error::other)?;
+        tx.pragma_update(None, "user_version", version as u32 + 1)
+            .map_err(io::Error::other)?;
+        tx.commit().map_err(io::Error::other)?;
+    }
+}
+
+fn player_exists(conn: &Connection, id: i32) -> rusqlite::Result<bool> {
+    Ok(conn
+        .query_row("SELECT 1 FROM players WHERE id = ?1", [id], |_| Ok(()))
+        .optional()?
+        .is_some())
+}
+
+fn record_from_row(row: &Row<'_>) -> rusqlite::Result<PlayerRecord> {
+    Ok(PlayerRecord {
+        score: row.get(0)?,
+        time: row.get(1)?,
+        timestamp: row.get(2)?,
+        board_size: row.get(3)?,
+        seed: row.get(4)?,
+        undo_count: row.get(5)?,
+        won: row.get(6)?,
+        max_tile: row.get(7)?,
+        moves: row.get(8)?,
+        merges: row.get(9)?,
+        moves_up: row.get(10)?,
+        moves_down: row.get(11)?,
+        moves_left: row.get(12)?,
+        moves_right: row.get(13)?,
+        mode: row.get(14)?,
+        puzzle: row.get(15)?,
+    })
+}
+
+/// 数据库忙超过等待时间时按超时处理，其他错误附带数据库的错误消息
+fn db_error(e: rusqlite::Error) -> DataError {
+    match e.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => DataError::Timeout,
+        _ => DataError::Backend(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(name: &str) -> SqliteDataManager {
+        let dir = std::env::temp_dir().join(format!("tui2048-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        SqliteDataManager::open_at(dir.join("tui2048.db")).unwrap()
+    }
+
+    fn add_player(data: &mut SqliteDataManager, name: &str) -> i32 {
+        data.conn
+            .execute("INSERT INTO players (name) VALUES (?1)", [name])
+            .unwrap();
+        data.conn.last_insert_rowid() as i32
+    }
+
+    fn record(mode: GameMode, board_size: i32, score: i32, undo_count: i32) -> PlayerRecord {
+        PlayerRecord {
+            score,
+            time: 100 - score as i64 / 10,
+            board_size,
+            seed: 7,
+            undo_count,
+            mode: mode.id(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn leaderboard_keeps_the_best_record_per_player() {
+        let mut data = open("leaderboard");
+        let me = add_player(&mut data, "我");
+        let a = add_player(&mut data, "甲");
+        let b = add_player(&mut data, "乙");
+        let c = add_player(&mut data, "丙");
+        let save = |data: &mut SqliteDataManager, id, records| {
+            data.save_record(Player {
+                id,
+                records,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        save(&mut data, me, vec![record(GameMode::Classic, 4, 9000, 0)]);
+        save(
+            &mut data,
+            a,
+            vec![
+                record(GameMode::Classic, 4, 300, 0),
+                record(GameMode::Classic, 4, 500, 0),
+                record(GameMode::Classic, 4, 8000, 2),
+                record(GameMode::Classic, 5, 7000, 0),
+            ],
+        );
+        save(
+            &mut data,
+            b,
+            vec![
+                record(GameMode::Classic, 4, 800, 0),
+                record(GameMode::TimeAttack, 4, 9000, 0),
+            ],
+        );
+        save(&mut data, c, vec![record(GameMode::Daily, 4, 100, 0)]);
+        data.current = Some(me);
+
+        let players = data
+            .get_leaderboard(Leaderboard::Board {
+                mode: GameMode::Classic,
+                board_size: 4,
+            })
+            .unwrap();
+        let rows = players
+            .iter()
+            .map(|x| (x.id, x.best_score, x.records.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, [(b, 800, 1), (a, 500, 1)]);
+
+        let players = data
+            .get_leaderboard(Leaderboard::Daily { seed: 7 })
+            .unwrap();
+        assert_eq!(players.iter().map(|x| x.id).collect::<Vec<_>>(), [c]);
+        assert!(
+            data.get_leaderboard(Leaderboard::Daily { seed: 8 })
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn rename_and_remove_leave_other_records_alone() {
+        let mut data = open("rename");
+        let id = add_player(&mut data, "甲");
+        add_player(&mut data, "乙");
+        let mut other = SqliteDataManager::open_at(data.conn.path().unwrap().into()).unwrap();
+        let player = |name: &str, records| Player {
+            id,
+            name: name.to_string(),
+            records,
+            ..Default::default()
+        };
+        let first = record(GameMode::Classic, 4, 100, 0);
+        data.save_record(player("甲", vec![first.clone(), first.clone()]))
+            .unwrap();
+        // 另一个进程在这之后写入的记录
+        other
+            .save_record(player("甲", vec![record(GameMode::Classic, 4, 200, 0)]))
+            .unwrap();
+
+        data.update_player(player("丙", Vec::new())).unwrap();
+        assert_eq!(
+            data.update_player(player("乙", Vec::new())),
+            Err(DataError::Conflict(String::from("已经有名为 乙 的玩家")))
+        );
+        data.remove_record(player("丙", vec![first])).unwrap();
+
+        let saved = data.query_player(id).unwrap().unwrap();
+        assert_eq!(saved.name, "丙");
+        let scores = saved.records.iter().map(|x| x.score).collect::<Vec<_>>();
+        assert_eq!(scores, [100, 200]);
+        assert_eq!(
+            data.remove_record(Player {
+                id: 99,
+                ..Default::default()
+            }),
+            Err(DataError::NotFound)
+        );
+    }
+
+    #[test]
+    fn no_current_player_without_login() {
+        let mut data = open("current");
+        assert_eq!(data.get_current_player(), Err(DataError::NotFound));
+        let id = add_player(&mut data, "甲");
+        data.current = Some(id);
+        assert_eq!(data.get_current_player().unwrap().name, "甲");
+        data.conn
+            .execute("DELETE FROM players WHERE id = ?1", [id])
+            .unwrap();
+        assert_eq!(data.get_current_player(), Err(DataError::NotFound));
+    }
+
+    #[test]
+    fn leaderboard_matches_the_file_backend() {
+        use crate::local::file::FileDataManager;
+
+        let mut sqlite = open("ties");
+        let path = std::env::temp_dir()
+            .join(format!("tui2048-{}-ties-file", std::process::id()))
+            .join("players.t2d");
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+        let mut text = String::from("T2048D 2\nnext 4\n");
+        for (id, name) in [(1, "甲"), (2, "乙"), (3, "丙")] {
+            assert_eq!(add_player(&mut sqlite, name), id);
+            text.push_str(&format!("player {id} salt hash {name}\n"));
+        }
+        tui2048::write_atomic(&path, &text).unwrap();
+        let mut file = FileDataManager::open_at(path.clone()).unwrap();
+
+        // 同分同用时的记录，以及只差用时的记录
+        let tie = |score, time, timestamp| PlayerRecord {
+            score,
+            time,
+            timestamp,
+            board_size: 4,
+            ..Default::default()
+        };
+        let fixture = [
+            (1, vec![tie(100, 50, 1), tie(100, 80, 2), tie(100, 80, 3)]),
+            (2, vec![tie(100, 80, 4)]),
+            (3, vec![tie(100, 60, 5), tie(50, 90, 6)]),
+        ];
+        let leaderboard = Leaderboard::Board {
+            mode: GameMode::Classic,
+            board_size: 4,
+        };
+        let mut results = Vec::new();
+        for data in [&mut sqlite as &mut dyn DataManager, &mut file] {
+            for (id, records) in &fixture {
+                data.save_record(Player {
+                    id: *id,
+                    records: records.clone(),
+                    ..Default::default()
+                })
+                .unwrap();
+            }
+            let rows = data
+                .get_leaderboard(leaderboard)
+                .unwrap()
+                .into_iter()
+                .map(|x| (x.id, x.best_time, x.records[0].timestamp))
+                .collect::<Vec<_>>();
+            results.push(rows);
+        }
+        assert_eq!(results[0], [(1, 80, 3), (2, 80, 4), (3, 60, 5)]);
+        assert_eq!(results[0], results[1]);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,8 +1,9 @@
 use std::io::Result;
 
-use app::{data::dummy::DummyDataManager, entry::leave};
+use local::{file::FileDataManager, sqlite::SqliteDataManager};
+use tui2048::{DataManager, leave};
 
-mod app;
+mod local;
 
 fn main() -> Result<()> {
     std::panic::set_hook(Box::new(|panic_info| {
@@ -12,7 +13,11 @@ fn main() -> Result<()> {
         println!("😱😱😱😱😱😱😱😱😱😱😱😱😱");
     }));
 
-    let data_app = Box::new(DummyDataManager);
-    app::entry::run_app(data_app)?;
+    // 默认保存在数据文件中，`--sqlite` 改用可以多人同时使用的数据库
+    let data_app: Box<dyn DataManager> = match std::env::args().nth(1).as_deref() {
+        Some("--sqlite") => Box::new(SqliteDataManager::open()?),
+        _ => Box::new(FileDataManager::open()?),
+    };
+    tui2048::run_app(data_app)?;
     Ok(())
 }